@@ -0,0 +1,124 @@
+use crate::error::RuntimeError;
+
+use std::path::{Path, PathBuf};
+
+// what the I/O natives may do, a denied call fails with an error naming the missing capability
+// a new interpreter may only read the clock, Capabilities::all() lifts every restriction
+#[derive(Debug, Clone, Default)]
+pub struct Capabilities {
+    all: bool,
+    read_roots: Vec<PathBuf>,
+    write_roots: Vec<PathBuf>,
+    env_vars: Vec<String>,
+    process: bool,
+    clock: bool,
+}
+
+impl Capabilities {
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    pub fn all() -> Self {
+        Self {
+            all: true,
+            ..Self::default()
+        }
+    }
+
+    // files anywhere under the directory may be read
+    pub fn allow_read(mut self, root: impl AsRef<Path>) -> Self {
+        self.read_roots.push(normalize(root.as_ref()));
+        self
+    }
+
+    // files anywhere under the directory may be created or overwritten
+    pub fn allow_write(mut self, root: impl AsRef<Path>) -> Self {
+        self.write_roots.push(normalize(root.as_ref()));
+        self
+    }
+
+    pub fn allow_env(mut self, name: &str) -> Self {
+        self.env_vars.push(name.to_owned());
+        self
+    }
+
+    pub fn allow_process(mut self) -> Self {
+        self.process = true;
+        self
+    }
+
+    pub fn allow_clock(mut self) -> Self {
+        self.clock = true;
+        self
+    }
+
+    pub fn check_read(&self, path: &Path) -> Result<PathBuf, RuntimeError<'static>> {
+        self.check_path(path, &self.read_roots, "read")
+    }
+
+    pub fn check_write(&self, path: &Path) -> Result<PathBuf, RuntimeError<'static>> {
+        self.check_path(path, &self.write_roots, "write")
+    }
+
+    pub fn check_env(&self, name: &str) -> Result<(), RuntimeError<'static>> {
+        if self.all || self.env_vars.iter().any(|var| var == name) {
+            Ok(())
+        } else {
+            Err(denied(format!("environment variable '{}'", name)))
+        }
+    }
+
+    pub fn check_process(&self) -> Result<(), RuntimeError<'static>> {
+        if self.all || self.process {
+            Ok(())
+        } else {
+            Err(denied("process spawning".into()))
+        }
+    }
+
+    pub fn check_clock(&self) -> Result<(), RuntimeError<'static>> {
+        if self.all || self.clock {
+            Ok(())
+        } else {
+            Err(denied("clock".into()))
+        }
+    }
+
+    // the path is resolved (following symlinks and ..) before it is compared against the roots
+    // a path that can't be resolved is denied
+    fn check_path(
+        &self,
+        path: &Path,
+        roots: &[PathBuf],
+        access: &str,
+    ) -> Result<PathBuf, RuntimeError<'static>> {
+        if self.all {
+            return Ok(path.to_owned());
+        }
+        match resolve(path) {
+            Some(resolved) if roots.iter().any(|root| resolved.starts_with(root)) => Ok(resolved),
+            _ => Err(denied(format!("{} access to '{}'", access, path.display()))),
+        }
+    }
+}
+
+fn denied(capability: String) -> RuntimeError<'static> {
+    RuntimeError::new(format!("Missing capability: {}", capability))
+}
+
+fn normalize(root: &Path) -> PathBuf {
+    root.canonicalize().unwrap_or_else(|_| root.to_owned())
+}
+
+// a file that doesn't exist yet is resolved through its parent directory
+fn resolve(path: &Path) -> Option<PathBuf> {
+    if let Ok(resolved) = path.canonicalize() {
+        return Some(resolved);
+    }
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    Some(parent.canonicalize().ok()?.join(path.file_name()?))
+}
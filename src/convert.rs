@@ -0,0 +1,163 @@
+use crate::error::RuntimeError;
+use crate::value::{HostObject, Value};
+
+use crate::sync::{Lock, Shared};
+
+// conversions between Rust types and script values, used for globals and native function arguments
+
+pub trait IntoLox {
+    fn into_lox(self) -> Value;
+}
+
+pub trait FromLox: Sized {
+    fn from_lox(value: Value) -> Result<Self, RuntimeError<'static>>;
+}
+
+fn type_error(expected: &str, value: &Value) -> RuntimeError<'static> {
+    RuntimeError::new(format!(
+        "Expected {} but got {}",
+        expected,
+        value.type_name()
+    ))
+}
+
+impl IntoLox for Value {
+    fn into_lox(self) -> Value {
+        self
+    }
+}
+
+impl FromLox for Value {
+    fn from_lox(value: Value) -> Result<Self, RuntimeError<'static>> {
+        Ok(value)
+    }
+}
+
+impl IntoLox for f64 {
+    fn into_lox(self) -> Value {
+        Value::Number(self)
+    }
+}
+
+impl FromLox for f64 {
+    fn from_lox(value: Value) -> Result<Self, RuntimeError<'static>> {
+        match value {
+            Value::Number(n) => Ok(n),
+            _ => Err(type_error("a number", &value)),
+        }
+    }
+}
+
+impl IntoLox for i64 {
+    fn into_lox(self) -> Value {
+        Value::Number(self as f64)
+    }
+}
+
+impl FromLox for i64 {
+    fn from_lox(value: Value) -> Result<Self, RuntimeError<'static>> {
+        match value {
+            // i64::MAX as f64 rounds up to 2^63, which is out of range, while -2^63 is i64::MIN
+            Value::Number(n) if n.fract() == 0.0 && n >= i64::MIN as f64 && n < i64::MAX as f64 => {
+                Ok(n as i64)
+            }
+            _ => Err(type_error("an integer", &value)),
+        }
+    }
+}
+
+impl IntoLox for bool {
+    fn into_lox(self) -> Value {
+        Value::Boolean(self)
+    }
+}
+
+impl FromLox for bool {
+    fn from_lox(value: Value) -> Result<Self, RuntimeError<'static>> {
+        match value {
+            Value::Boolean(b) => Ok(b),
+            _ => Err(type_error("a boolean", &value)),
+        }
+    }
+}
+
+impl IntoLox for String {
+    fn into_lox(self) -> Value {
+        Value::String(self)
+    }
+}
+
+impl IntoLox for &str {
+    fn into_lox(self) -> Value {
+        Value::String(self.to_owned())
+    }
+}
+
+impl FromLox for String {
+    fn from_lox(value: Value) -> Result<Self, RuntimeError<'static>> {
+        match value {
+            Value::String(s) => Ok(s),
+            _ => Err(type_error("a string", &value)),
+        }
+    }
+}
+
+impl IntoLox for () {
+    fn into_lox(self) -> Value {
+        Value::Nil
+    }
+}
+
+impl FromLox for () {
+    fn from_lox(value: Value) -> Result<Self, RuntimeError<'static>> {
+        match value {
+            Value::Nil => Ok(()),
+            _ => Err(type_error("nil", &value)),
+        }
+    }
+}
+
+// None is nil
+impl<T: IntoLox> IntoLox for Option<T> {
+    fn into_lox(self) -> Value {
+        match self {
+            Some(value) => value.into_lox(),
+            None => Value::Nil,
+        }
+    }
+}
+
+impl<T: FromLox> FromLox for Option<T> {
+    fn from_lox(value: Value) -> Result<Self, RuntimeError<'static>> {
+        match value {
+            Value::Nil => Ok(None),
+            value => Ok(Some(T::from_lox(value)?)),
+        }
+    }
+}
+
+impl<T: IntoLox> IntoLox for Vec<T> {
+    fn into_lox(self) -> Value {
+        let values = self.into_iter().map(IntoLox::into_lox).collect();
+        Value::List(Shared::new(Lock::new(values)))
+    }
+}
+
+// accepts lists and tuples
+impl<T: FromLox> FromLox for Vec<T> {
+    fn from_lox(value: Value) -> Result<Self, RuntimeError<'static>> {
+        let values = match &value {
+            Value::List(list) => list.borrow().clone(),
+            Value::Tuple(tuple) => tuple.to_vec(),
+            _ => return Err(type_error("a list", &value)),
+        };
+        values.into_iter().map(T::from_lox).collect()
+    }
+}
+
+// the caller keeps its handle, so changes made by the script are visible to the host
+impl<T: HostObject + 'static> IntoLox for Shared<T> {
+    fn into_lox(self) -> Value {
+        Value::HostObject(self)
+    }
+}
@@ -0,0 +1,117 @@
+use crate::capabilities::Capabilities;
+use crate::convert::{FromLox, IntoLox};
+use crate::error::{LoxError, RuntimeError};
+use crate::interpreter::{CancelHandle, Interpreter, Limits};
+use crate::program::Program;
+use crate::streams::Streams;
+use crate::value::Value;
+
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::time::Duration;
+
+// embeddable interpreter session: globals and natives persist between evaluations
+pub struct Lox {
+    interpreter: Interpreter,
+}
+
+impl Default for Lox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lox {
+    pub fn new() -> Self {
+        Self {
+            interpreter: Interpreter::new(),
+        }
+    }
+
+    // scans, parses and evaluates the source, returning the value of the expression
+    pub fn eval(&mut self, source: &str) -> Result<Value, LoxError<'static>> {
+        self.run(&Program::compile(source)?)
+    }
+
+    // evaluates an already parsed program, which may be shared with other sessions
+    pub fn run(&mut self, program: &Program) -> Result<Value, LoxError<'static>> {
+        self.interpreter.run(program)
+    }
+
+    pub fn exec_file(&mut self, path: impl AsRef<Path>) -> Result<Value, LoxError<'static>> {
+        let source = std::fs::read_to_string(path)?;
+        self.eval(&source)
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<&Value> {
+        self.interpreter.global(name)
+    }
+
+    pub fn set_global(&mut self, name: &str, value: impl IntoLox) {
+        self.interpreter.define(name, value.into_lox());
+    }
+
+    // typed lookup of a global, e.g. lox.get::<f64>("total")
+    pub fn get<T: FromLox>(&self, name: &str) -> Result<T, LoxError<'static>> {
+        let value = self
+            .get_global(name)
+            .ok_or_else(|| RuntimeError::new(format!("Undefined variable '{}'", name)))?;
+        Ok(T::from_lox(value.clone())?)
+    }
+
+    pub fn define_native<F>(&mut self, name: &str, arity: usize, function: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, RuntimeError<'static>> + Send + Sync + 'static,
+    {
+        self.interpreter.define_native(name, arity, function);
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.interpreter.limits()
+    }
+
+    // applies to every later eval, going over a limit ends that eval with a *Limit error
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.interpreter.set_limits(limits);
+    }
+
+    // cancelling through the handle stops the running eval with LoxError::Cancelled, or the next
+    // eval when none is running, the session stays usable for the eval after that
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.interpreter.cancel_handle()
+    }
+
+    // each eval that runs longer than this ends with LoxError::Timeout
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.interpreter.set_timeout(timeout);
+    }
+
+    // what the I/O natives may access, e.g. Capabilities::none().allow_read("data")
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.interpreter.set_capabilities(capabilities);
+    }
+
+    pub fn streams(&self) -> &Streams {
+        self.interpreter.streams()
+    }
+
+    // where the REPL and the run_* helpers write their results
+    pub fn set_output(&mut self, output: impl Write + Send + Sync + 'static) {
+        self.streams().set_output(output);
+    }
+
+    // where the REPL reports errors
+    pub fn set_diagnostics(&mut self, diagnostics: impl Write + Send + Sync + 'static) {
+        self.streams().set_diagnostics(diagnostics);
+    }
+
+    // where input() and the REPL read lines from
+    pub fn set_input(&mut self, input: impl BufRead + Send + Sync + 'static) {
+        self.streams().set_input(input);
+    }
+
+    // for advanced users driving Scanner, Parser and Expr themselves
+    pub fn interpreter(&mut self) -> &mut Interpreter {
+        &mut self.interpreter
+    }
+}
@@ -11,6 +11,15 @@ pub enum LoxError<'a> {
     Lexer(Vec<LexError>),
     Parser(Vec<ParseError<'a>>),
     Runtime(RuntimeError<'a>),
+    // the script went over one of the interpreter's execution limits
+    StepLimit(RuntimeError<'a>),
+    CallDepthLimit(RuntimeError<'a>),
+    MemoryLimit(RuntimeError<'a>),
+    // stopped through a CancelHandle
+    Cancelled(RuntimeError<'a>),
+    // ran past the interpreter's timeout
+    Timeout(RuntimeError<'a>),
+    Io(std::io::Error),
 }
 
 impl fmt::Display for LoxError<'_> {
@@ -31,6 +40,24 @@ impl fmt::Display for LoxError<'_> {
             LoxError::Runtime(error) => {
                 write!(f, "Runtime Error: {}", error)?;
             }
+            LoxError::StepLimit(error) => {
+                write!(f, "Step Limit Error: {}", error)?;
+            }
+            LoxError::CallDepthLimit(error) => {
+                write!(f, "Call Depth Limit Error: {}", error)?;
+            }
+            LoxError::MemoryLimit(error) => {
+                write!(f, "Memory Limit Error: {}", error)?;
+            }
+            LoxError::Cancelled(error) => {
+                write!(f, "Cancelled: {}", error)?;
+            }
+            LoxError::Timeout(error) => {
+                write!(f, "Timeout Error: {}", error)?;
+            }
+            LoxError::Io(error) => {
+                write!(f, "IO Error: {}", error)?;
+            }
         }
         Ok(())
     }
@@ -38,16 +65,27 @@ impl fmt::Display for LoxError<'_> {
 
 impl std::error::Error for LoxError<'_> {}
 
+impl From<std::io::Error> for LoxError<'_> {
+    fn from(error: std::io::Error) -> Self {
+        LoxError::Io(error)
+    }
+}
+
 // Lexer Error
 #[derive(Debug)]
 pub struct LexError {
     pub line: usize,
+    pub column: usize,
     pub msg: &'static str,
 }
 
 impl fmt::Display for LexError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Line: {}, Cause: {}", self.line, self.msg)?;
+        write!(
+            f,
+            "Line: {}, Column: {}, Cause: {}",
+            self.line, self.column, self.msg
+        )?;
         Ok(())
     }
 }
@@ -62,7 +100,7 @@ impl From<Vec<LexError>> for LoxError<'_> {
 #[derive(Debug)]
 pub struct ParseError<'a> {
     pub token: Token<'a>,
-    pub msg: &'static str,
+    pub msg: String,
 }
 
 impl fmt::Display for ParseError<'_> {
@@ -70,13 +108,13 @@ impl fmt::Display for ParseError<'_> {
         let lexeme = if self.token.token_type == TokenType::EOF {
             "eof"
         } else {
-            &String::from_utf8_lossy(self.token.lexeme)
+            &String::from_utf8_lossy(&self.token.lexeme)
         };
 
         write!(
             f,
-            "Line: {}, Token: {}, Cause: {}",
-            self.token.line, lexeme, self.msg
+            "Line: {}, Column: {}, Token: {}, Cause: {}",
+            self.token.line, self.token.column, lexeme, self.msg
         )?;
         Ok(())
     }
@@ -88,22 +126,75 @@ impl<'a> From<Vec<ParseError<'a>>> for LoxError<'a> {
     }
 }
 
+// a trace longer than this shows only its innermost and outermost calls, deep recursion would
+// otherwise print a line for each of its calls
+const MAX_TRACE_LINES: usize = 12;
+const OUTERMOST_TRACE_LINES: usize = 2;
+
 // Runtime Error
 #[derive(Debug)]
 pub struct RuntimeError<'a> {
+    // None until an error raised inside a native function is given the location of its call
+    pub token: Option<Token<'a>>,
+    pub msg: String,
+    // the calls the error was raised in, innermost first, empty for an error at the top level
+    pub trace: Vec<CallFrame<'a>>,
+}
+
+// a call on the stack: the function called, as written at the call, and the call's ')' token
+#[derive(Debug)]
+pub struct CallFrame<'a> {
+    pub function: String,
     pub token: Token<'a>,
-    pub msg: &'static str,
+}
+
+impl RuntimeError<'_> {
+    // for native functions, which don't know where they were called from
+    pub fn new(msg: impl Into<String>) -> Self {
+        RuntimeError {
+            token: None,
+            msg: msg.into(),
+            trace: Vec::new(),
+        }
+    }
+
+    // the error on its way out of a call, which was made inside all the calls traced so far
+    pub(crate) fn unwind_call(mut self, function: String, token: &Token<'_>) -> Self {
+        self.trace.push(CallFrame {
+            function,
+            token: token.deep_clone(),
+        });
+        self
+    }
 }
 
 impl fmt::Display for RuntimeError<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{}\n[line: {}, operator: {}]",
-            self.msg,
-            self.token.line,
-            &String::from_utf8_lossy(self.token.lexeme)
-        )?;
+        write!(f, "{}", self.msg)?;
+        if let Some(token) = &self.token {
+            write!(
+                f,
+                "\n[line: {}, column: {}, operator: {}]",
+                token.line,
+                token.column,
+                &String::from_utf8_lossy(&token.lexeme)
+            )?;
+        }
+        let omitted = self.trace.len().saturating_sub(MAX_TRACE_LINES);
+        let innermost = MAX_TRACE_LINES - OUTERMOST_TRACE_LINES;
+        for (i, frame) in self.trace.iter().enumerate() {
+            if omitted > 0 && i >= innermost && i < innermost + omitted {
+                if i == innermost {
+                    write!(f, "\n  ... {} more calls", omitted)?;
+                }
+                continue;
+            }
+            write!(
+                f,
+                "\n  in {}, called at [line: {}, column: {}]",
+                frame.function, frame.token.line, frame.token.column
+            )?;
+        }
         Ok(())
     }
 }
@@ -1,16 +1,323 @@
 use crate::error::RuntimeError;
+use crate::interpreter::{Interpreter, Scope};
+use crate::iteration::Iteration;
+use crate::sync::{Lock, Shared};
 use crate::token::{Token, TokenType};
-use crate::value::Value;
+use crate::value::{Ancestors, Closure, Map, Value};
+
+use std::collections::HashMap;
+use std::ops::Range;
 
 pub enum Expr<'token, 'lexeme> {
     Literal(Value),
+    Variable(&'token Token<'lexeme>),
+    // callee, ')' token, arguments, named arguments
+    Call(
+        Box<Expr<'token, 'lexeme>>,
+        &'token Token<'lexeme>,
+        Vec<Expr<'token, 'lexeme>>,
+        Vec<(&'token Token<'lexeme>, Expr<'token, 'lexeme>)>,
+    ),
     Unary(&'token Token<'lexeme>, Box<Expr<'token, 'lexeme>>),
+    // a chain of left-associative operators, a + b - c is (a, [(+, b), (-, c)])
+    // kept flat so that long chains are parsed and evaluated without recursion
     Binary(
+        Box<Expr<'token, 'lexeme>>,
+        Vec<(&'token Token<'lexeme>, Expr<'token, 'lexeme>)>,
+    ),
+    Grouping(Box<Expr<'token, 'lexeme>>),
+    Ternary(
+        Box<Expr<'token, 'lexeme>>,
+        Box<Expr<'token, 'lexeme>>,
+        Box<Expr<'token, 'lexeme>>,
+    ),
+    // a, b, c is one flat list for the same reason
+    Comma(Vec<Expr<'token, 'lexeme>>),
+    // string literal segments interleaved with embedded expressions
+    Interpolation(Vec<Expr<'token, 'lexeme>>),
+    List(Vec<Expr<'token, 'lexeme>>),
+    // elements, '(' token
+    Tuple(Vec<Expr<'token, 'lexeme>>, &'token Token<'lexeme>),
+    // key, ':' token, value
+    Map(
+        Vec<(
+            Expr<'token, 'lexeme>,
+            &'token Token<'lexeme>,
+            Expr<'token, 'lexeme>,
+        )>,
+    ),
+    // object, '[' token, index
+    Index(
         Box<Expr<'token, 'lexeme>>,
         &'token Token<'lexeme>,
         Box<Expr<'token, 'lexeme>>,
     ),
-    Grouping(Box<Expr<'token, 'lexeme>>),
+    // object, property name
+    Get(Box<Expr<'token, 'lexeme>>, &'token Token<'lexeme>),
+    // object, '[' token, start, end
+    Slice(
+        Box<Expr<'token, 'lexeme>>,
+        &'token Token<'lexeme>,
+        Option<Box<Expr<'token, 'lexeme>>>,
+        Option<Box<Expr<'token, 'lexeme>>>,
+    ),
+    // plain (=) and compound (+=, ..., prefix ++/--) assignment, evaluates to the stored value
+    Assign(
+        Target<'token, 'lexeme>,
+        &'token Token<'lexeme>,
+        Box<Expr<'token, 'lexeme>>,
+    ),
+    // postfix ++/--, evaluates to the value before the update
+    Postfix(Target<'token, 'lexeme>, &'token Token<'lexeme>),
+    // scrutinee, 'match' token, arms
+    Match(
+        Box<Expr<'token, 'lexeme>>,
+        &'token Token<'lexeme>,
+        Vec<MatchArm<'token, 'lexeme>>,
+    ),
+    // label, loop variable, 'in' token, iterable, body; evaluates to nil
+    For(
+        Option<&'token Token<'lexeme>>,
+        &'token Token<'lexeme>,
+        &'token Token<'lexeme>,
+        Box<Expr<'token, 'lexeme>>,
+        Box<Expr<'token, 'lexeme>>,
+    ),
+    // keyword, label of the loop it leaves (the innermost one when None)
+    Break(&'token Token<'lexeme>, Option<&'token Token<'lexeme>>),
+    Continue(&'token Token<'lexeme>, Option<&'token Token<'lexeme>>),
+    // keyword, thrown value
+    Throw(&'token Token<'lexeme>, Box<Expr<'token, 'lexeme>>),
+    // body, catch variable and block, finally block; evaluates to the body's or the catch's value
+    Try(
+        Box<Expr<'token, 'lexeme>>,
+        Option<(&'token Token<'lexeme>, Box<Expr<'token, 'lexeme>>)>,
+        Option<Box<Expr<'token, 'lexeme>>>,
+    ),
+    // evaluates to a closure over the variables in scope
+    Function(Box<Function<'token, 'lexeme>>),
+}
+
+// fun (a, b = 2, ...rest) { body } or (a, b = 2, ...rest) => body
+pub struct Function<'token, 'lexeme> {
+    // 'fun' or '(' token, where the function is said to be
+    pub token: &'token Token<'lexeme>,
+    pub params: Vec<Param<'token, 'lexeme>>,
+    // collects the positional arguments after the params into a list
+    pub rest: Option<&'token Token<'lexeme>>,
+    pub body: Expr<'token, 'lexeme>,
+    // numbered in the order the parser creates them, a closure refers to its function by this
+    pub(crate) id: usize,
+}
+
+pub struct Param<'token, 'lexeme> {
+    pub name: &'token Token<'lexeme>,
+    // evaluated in the scope of the call when the argument is left out
+    pub default: Option<Expr<'token, 'lexeme>>,
+}
+
+impl Function<'_, '_> {
+    fn pretty_print(&self) -> String {
+        let params = self.params.iter().map(|param| {
+            let name = String::from_utf8_lossy(&param.name.lexeme);
+            match &param.default {
+                Some(default) => format!("(= {} {})", name, default.pretty_print()),
+                None => name.into_owned(),
+            }
+        });
+        let rest = self
+            .rest
+            .map(|rest| "...".to_owned() + &String::from_utf8_lossy(&rest.lexeme));
+        "(fun (".to_owned()
+            + &params.chain(rest).collect::<Vec<_>>().join(" ")
+            + ") "
+            + &self.body.pretty_print()
+            + ")"
+    }
+
+    // the parameter list as written, e.g. (a, b = 2, ...rest), for errors about the arguments
+    pub(crate) fn signature(&self) -> String {
+        let params = self.params.iter().map(|param| {
+            let name = String::from_utf8_lossy(&param.name.lexeme);
+            match &param.default {
+                Some(default) => format!("{} = {}", name, default.pretty_print()),
+                None => name.into_owned(),
+            }
+        });
+        let rest = self
+            .rest
+            .map(|rest| "...".to_owned() + &String::from_utf8_lossy(&rest.lexeme));
+        "(".to_owned() + &params.chain(rest).collect::<Vec<_>>().join(", ") + ")"
+    }
+
+    // how many positional arguments a call may pass
+    fn arity(&self) -> String {
+        let required = self
+            .params
+            .iter()
+            .filter(|param| param.default.is_none())
+            .count();
+        if self.rest.is_some() {
+            format!("at least {} argument(s)", required)
+        } else if required == self.params.len() {
+            format!("{} argument(s)", required)
+        } else {
+            format!("{} to {} argument(s)", required, self.params.len())
+        }
+    }
+}
+
+// why an expression stopped before producing a value: an error, or a break or continue on its way
+// out to the loop it belongs to
+pub(crate) enum Unwind<'token, 'lexeme, 'err> {
+    // boxed, as every evaluation result on the stack is as large as this enum
+    Error(Box<RuntimeError<'err>>),
+    // keyword, label
+    Break(&'token Token<'lexeme>, Option<&'token Token<'lexeme>>),
+    Continue(&'token Token<'lexeme>, Option<&'token Token<'lexeme>>),
+}
+
+impl<'err> From<RuntimeError<'err>> for Unwind<'_, '_, 'err> {
+    fn from(error: RuntimeError<'err>) -> Self {
+        Unwind::Error(Box::new(error))
+    }
+}
+
+type Evaluation<'token, 'lexeme, 'err> = Result<Value, Unwind<'token, 'lexeme, 'err>>;
+
+// the variables of a call and the parameters to give their default values, by name
+type Bindings<'a> = (HashMap<String, Value>, Vec<(String, &'a Expr<'a, 'a>)>);
+
+pub struct MatchArm<'token, 'lexeme> {
+    pub pattern: Pattern,
+    pub guard: Option<Expr<'token, 'lexeme>>,
+    pub body: Expr<'token, 'lexeme>,
+}
+
+pub enum Pattern {
+    Wildcard,
+    Literal(Value),
+    // start (inclusive) and end (exclusive)
+    Range(f64, f64),
+    List(Vec<Pattern>),
+    Tuple(Vec<Pattern>),
+    // a map matches when it has all listed keys with matching values, other keys are ignored
+    Map(Vec<(Value, Pattern)>),
+}
+
+impl Pattern {
+    fn pretty_print(&self) -> String {
+        let join = |patterns: &Vec<Pattern>| {
+            patterns
+                .iter()
+                .map(|pattern| " ".to_owned() + &pattern.pretty_print())
+                .collect::<String>()
+        };
+        match self {
+            Self::Wildcard => "_".to_owned(),
+            Self::Literal(val) => val.to_string(),
+            Self::Range(start, end) => format!("{}..{}", start, end),
+            Self::List(patterns) => "(list".to_owned() + &join(patterns) + ")",
+            Self::Tuple(patterns) => "(tuple".to_owned() + &join(patterns) + ")",
+            Self::Map(entries) => {
+                "(map".to_owned()
+                    + &entries
+                        .iter()
+                        .map(|(key, pattern)| format!(" ({} {})", key, pattern.pretty_print()))
+                        .collect::<String>()
+                    + ")"
+            }
+        }
+    }
+
+    fn matches(&self, value: &Value) -> bool {
+        let all_match = |patterns: &Vec<Pattern>, values: &[Value]| {
+            patterns.len() == values.len()
+                && patterns
+                    .iter()
+                    .zip(values)
+                    .all(|(pattern, value)| pattern.matches(value))
+        };
+        match (self, value) {
+            (Self::Wildcard, _) => true,
+            (Self::Literal(literal), _) => Expr::is_equal(literal, value),
+            (Self::Range(start, end), Value::Number(n)) => start <= n && n < end,
+            (Self::List(patterns), Value::List(list)) => all_match(patterns, &list.borrow()),
+            (Self::Tuple(patterns), Value::Tuple(tuple)) => all_match(patterns, tuple),
+            (Self::Map(entries), Value::Map(map)) => {
+                let map = map.borrow();
+                entries.iter().all(|(key, pattern)| match map.get(key) {
+                    Ok(Some(value)) => pattern.matches(value),
+                    _ => false,
+                })
+            }
+            _ => false,
+        }
+    }
+}
+
+// the place an assignment writes to, its subexpressions are evaluated only once
+pub enum Target<'token, 'lexeme> {
+    // a global, = creates it if it doesn't exist yet
+    Variable(&'token Token<'lexeme>),
+    // object, '[' token, index
+    Index(
+        Box<Expr<'token, 'lexeme>>,
+        &'token Token<'lexeme>,
+        Box<Expr<'token, 'lexeme>>,
+    ),
+    // object, property name
+    Field(Box<Expr<'token, 'lexeme>>, &'token Token<'lexeme>),
+    // destructuring pattern, targets, '(' token
+    Tuple(Vec<Target<'token, 'lexeme>>, &'token Token<'lexeme>),
+}
+
+impl Target<'_, '_> {
+    fn pretty_print(&self) -> String {
+        match self {
+            Self::Variable(name) => String::from_utf8_lossy(&name.lexeme).into_owned(),
+            Self::Index(object, _, index) => {
+                "([] ".to_owned() + &object.pretty_print() + " " + &index.pretty_print() + ")"
+            }
+            Self::Field(object, name) => {
+                "(. ".to_owned()
+                    + &object.pretty_print()
+                    + " "
+                    + &String::from_utf8_lossy(&name.lexeme)
+                    + ")"
+            }
+            Self::Tuple(targets, _) => {
+                "(tuple".to_owned()
+                    + &targets
+                        .iter()
+                        .map(|target| " ".to_owned() + &target.pretty_print())
+                        .collect::<String>()
+                    + ")"
+            }
+        }
+    }
+}
+
+impl<'token, 'lexeme> Target<'token, 'lexeme> {
+    fn functions<'a>(&'a self, found: &mut Vec<&'a Function<'token, 'lexeme>>) {
+        match self {
+            Self::Variable(_) => (),
+            Self::Index(object, _, index) => {
+                object.functions(found);
+                index.functions(found);
+            }
+            Self::Field(object, _) => object.functions(found),
+            Self::Tuple(targets, _) => targets.iter().for_each(|target| target.functions(found)),
+        }
+    }
+}
+
+// an assignment target whose subexpressions have been evaluated
+enum Place<'token, 'lexeme> {
+    Variable(&'token Token<'lexeme>),
+    Index(Value, Value, &'token Token<'lexeme>),
+    Field(Value, &'token Token<'lexeme>),
+    Tuple(Vec<Place<'token, 'lexeme>>, &'token Token<'lexeme>),
 }
 
 impl<'token, 'lexeme, 'err> Expr<'token, 'lexeme> {
@@ -19,104 +326,1236 @@ impl<'token, 'lexeme, 'err> Expr<'token, 'lexeme> {
     pub fn pretty_print(&self) -> String {
         match self {
             Self::Literal(val) => val.to_string(),
+            Self::Variable(name) => String::from_utf8_lossy(&name.lexeme).into_owned(),
+            Self::Call(callee, _, arguments, named) => {
+                "(call ".to_owned()
+                    + &callee.pretty_print()
+                    + &arguments
+                        .iter()
+                        .map(|argument| " ".to_owned() + &argument.pretty_print())
+                        .collect::<String>()
+                    + &named
+                        .iter()
+                        .map(|(name, argument)| {
+                            " (".to_owned()
+                                + &String::from_utf8_lossy(&name.lexeme)
+                                + ": "
+                                + &argument.pretty_print()
+                                + ")"
+                        })
+                        .collect::<String>()
+                    + ")"
+            }
             Self::Unary(token, expr) => {
-                "(".to_owned() + &String::from_utf8_lossy(token.lexeme) + &expr.pretty_print() + ")"
+                "(".to_owned()
+                    + &String::from_utf8_lossy(&token.lexeme)
+                    + &expr.pretty_print()
+                    + ")"
+            }
+            // printed as nested left-associative operations
+            Self::Binary(first, operations) => {
+                operations
+                    .iter()
+                    .fold(first.pretty_print(), |left, (token, r_expr)| {
+                        "(".to_owned()
+                            + &String::from_utf8_lossy(&token.lexeme)
+                            + " "
+                            + &left
+                            + " "
+                            + &r_expr.pretty_print()
+                            + ")"
+                    })
+            }
+            Self::Grouping(expr) => "(".to_owned() + &expr.pretty_print() + ")",
+            Self::Ternary(cond, then_expr, else_expr) => {
+                "(?: ".to_owned()
+                    + &cond.pretty_print()
+                    + " "
+                    + &then_expr.pretty_print()
+                    + " "
+                    + &else_expr.pretty_print()
+                    + ")"
+            }
+            Self::Comma(exprs) => {
+                let (first, rest) = exprs.split_first().unwrap();
+                rest.iter().fold(first.pretty_print(), |left, r_expr| {
+                    "(, ".to_owned() + &left + " " + &r_expr.pretty_print() + ")"
+                })
+            }
+            Self::Interpolation(parts) => {
+                "(interpolate".to_owned()
+                    + &parts
+                        .iter()
+                        .map(|part| " ".to_owned() + &part.pretty_print())
+                        .collect::<String>()
+                    + ")"
+            }
+            Self::List(elements) => {
+                "(list".to_owned()
+                    + &elements
+                        .iter()
+                        .map(|element| " ".to_owned() + &element.pretty_print())
+                        .collect::<String>()
+                    + ")"
+            }
+            Self::Tuple(elements, _) => {
+                "(tuple".to_owned()
+                    + &elements
+                        .iter()
+                        .map(|element| " ".to_owned() + &element.pretty_print())
+                        .collect::<String>()
+                    + ")"
+            }
+            Self::Map(entries) => {
+                "(map".to_owned()
+                    + &entries
+                        .iter()
+                        .map(|(key, _, value)| {
+                            " (".to_owned()
+                                + &key.pretty_print()
+                                + " "
+                                + &value.pretty_print()
+                                + ")"
+                        })
+                        .collect::<String>()
+                    + ")"
+            }
+            Self::Index(object, _, index) => {
+                "([] ".to_owned() + &object.pretty_print() + " " + &index.pretty_print() + ")"
+            }
+            Self::Get(object, name) => {
+                "(. ".to_owned()
+                    + &object.pretty_print()
+                    + " "
+                    + &String::from_utf8_lossy(&name.lexeme)
+                    + ")"
             }
-            Self::Binary(l_expr, token, r_expr) => {
+            Self::Slice(object, _, start, end) => {
+                let bound = |expr: &Option<Box<Expr>>| match expr {
+                    Some(expr) => expr.pretty_print(),
+                    None => "_".to_owned(),
+                };
+                "([:] ".to_owned()
+                    + &object.pretty_print()
+                    + " "
+                    + &bound(start)
+                    + " "
+                    + &bound(end)
+                    + ")"
+            }
+            Self::Assign(target, token, value) => {
                 "(".to_owned()
-                    + &String::from_utf8_lossy(token.lexeme)
+                    + &String::from_utf8_lossy(&token.lexeme)
                     + " "
-                    + &l_expr.pretty_print()
+                    + &target.pretty_print()
                     + " "
-                    + &r_expr.pretty_print()
+                    + &value.pretty_print()
                     + ")"
             }
-            Self::Grouping(expr) => "(".to_owned() + &expr.pretty_print() + ")",
+            Self::Postfix(target, token) => {
+                "(".to_owned()
+                    + &target.pretty_print()
+                    + &String::from_utf8_lossy(&token.lexeme)
+                    + ")"
+            }
+            Self::Match(scrutinee, _, arms) => {
+                "(match ".to_owned()
+                    + &scrutinee.pretty_print()
+                    + &arms
+                        .iter()
+                        .map(|arm| {
+                            let guard = match &arm.guard {
+                                Some(guard) => " if ".to_owned() + &guard.pretty_print(),
+                                None => String::new(),
+                            };
+                            " (".to_owned()
+                                + &arm.pattern.pretty_print()
+                                + &guard
+                                + " => "
+                                + &arm.body.pretty_print()
+                                + ")"
+                        })
+                        .collect::<String>()
+                    + ")"
+            }
+            Self::For(label, name, _, iterable, body) => {
+                let label = match label {
+                    Some(label) => String::from_utf8_lossy(&label.lexeme) + ": ",
+                    None => "".into(),
+                };
+                "(".to_owned()
+                    + &label
+                    + "for "
+                    + &String::from_utf8_lossy(&name.lexeme)
+                    + " "
+                    + &iterable.pretty_print()
+                    + " "
+                    + &body.pretty_print()
+                    + ")"
+            }
+            Self::Break(keyword, label) | Self::Continue(keyword, label) => {
+                let label = match label {
+                    Some(label) => " ".to_owned() + &String::from_utf8_lossy(&label.lexeme),
+                    None => String::new(),
+                };
+                "(".to_owned() + &String::from_utf8_lossy(&keyword.lexeme) + &label + ")"
+            }
+            Self::Throw(_, value) => "(throw ".to_owned() + &value.pretty_print() + ")",
+            Self::Try(body, catch, finally) => {
+                let catch = match catch {
+                    Some((name, handler)) => {
+                        " (catch ".to_owned()
+                            + &String::from_utf8_lossy(&name.lexeme)
+                            + " "
+                            + &handler.pretty_print()
+                            + ")"
+                    }
+                    None => String::new(),
+                };
+                let finally = match finally {
+                    Some(finally) => " (finally ".to_owned() + &finally.pretty_print() + ")",
+                    None => String::new(),
+                };
+                "(try ".to_owned() + &body.pretty_print() + &catch + &finally + ")"
+            }
+            Self::Function(function) => function.pretty_print(),
         }
     }
 
-    pub fn interpret(self) -> Result<Value, RuntimeError<'err>> {
+    // every function expression in the tree, including those inside other functions
+    pub(crate) fn functions<'a>(&'a self, found: &mut Vec<&'a Function<'token, 'lexeme>>) {
+        let mut visit = |expr: &'a Expr<'token, 'lexeme>| expr.functions(found);
         match self {
-            Self::Literal(val) => Ok(val),
-            Self::Unary(token, expr) => {
-                let right = expr.interpret()?;
-                match (token.token_type, &right) {
-                    (TokenType::Minus, Value::Number(n)) => Ok(Value::Number(-n)),
-                    (TokenType::Bang, Value::Boolean(_)) => {
-                        Ok(Value::Boolean(!Expr::is_truthy(&right)))
-                    }
-                    _ => Err(RuntimeError {
-                        token: token.deep_clone(),
-                        msg: "Invalid unary expression",
-                    }),
+            Self::Literal(_) | Self::Variable(_) | Self::Break(..) | Self::Continue(..) => (),
+            Self::Call(callee, _, arguments, named) => {
+                visit(callee);
+                arguments.iter().for_each(&mut visit);
+                named.iter().for_each(|(_, argument)| visit(argument));
+            }
+            Self::Unary(_, expr)
+            | Self::Grouping(expr)
+            | Self::Get(expr, _)
+            | Self::Throw(_, expr) => visit(expr),
+            Self::Binary(first, operations) => {
+                visit(first);
+                operations.iter().for_each(|(_, expr)| visit(expr));
+            }
+            Self::Ternary(cond, then_expr, else_expr) => {
+                visit(cond);
+                visit(then_expr);
+                visit(else_expr);
+            }
+            Self::Comma(exprs)
+            | Self::Interpolation(exprs)
+            | Self::List(exprs)
+            | Self::Tuple(exprs, _) => exprs.iter().for_each(visit),
+            Self::Map(entries) => entries.iter().for_each(|(key, _, value)| {
+                visit(key);
+                visit(value);
+            }),
+            Self::Index(object, _, index) => {
+                visit(object);
+                visit(index);
+            }
+            Self::Slice(object, _, start, end) => {
+                visit(object);
+                start.iter().chain(end).for_each(|expr| visit(expr));
+            }
+            Self::Assign(target, _, value) => {
+                target.functions(found);
+                value.functions(found);
+            }
+            Self::Postfix(target, _) => target.functions(found),
+            Self::Match(scrutinee, _, arms) => {
+                visit(scrutinee);
+                arms.iter().for_each(|arm| {
+                    arm.guard.iter().for_each(&mut visit);
+                    visit(&arm.body);
+                });
+            }
+            Self::For(_, _, _, iterable, body) => {
+                visit(iterable);
+                visit(body);
+            }
+            Self::Try(body, catch, finally) => {
+                visit(body);
+                catch.iter().for_each(|(_, handler)| visit(handler));
+                finally.iter().for_each(|finally| visit(finally));
+            }
+            Self::Function(function) => {
+                found.push(function);
+                for param in &function.params {
+                    param
+                        .default
+                        .iter()
+                        .for_each(|default| default.functions(found));
                 }
+                function.body.functions(found);
             }
-            Self::Binary(l_expr, token, r_expr) => {
-                let left = l_expr.interpret()?;
-                let right = r_expr.interpret()?;
-                match (&left, token.token_type, &right) {
-                    // arithmetic operators
-                    (Value::Number(a), TokenType::Minus, Value::Number(b)) => {
-                        Ok(Value::Number(a - b))
-                    }
-                    (Value::Number(a), TokenType::Plus, Value::Number(b)) => {
-                        Ok(Value::Number(a + b))
-                    }
-                    (Value::String(s1), TokenType::Plus, Value::String(s2)) => {
-                        Ok(Value::String(s1.to_owned() + &s2))
-                    }
-                    (Value::Number(a), TokenType::Star, Value::Number(b)) => {
-                        Ok(Value::Number(a * b))
-                    }
-                    (Value::Number(a), TokenType::Slash, Value::Number(b)) => {
-                        Ok(Value::Number(a / b))
-                    }
-                    // comparison operators
-                    (Value::Number(a), TokenType::Greater, Value::Number(b)) => {
-                        Ok(Value::Boolean(a > b))
-                    }
-                    (Value::Number(a), TokenType::GreaterEqual, Value::Number(b)) => {
-                        Ok(Value::Boolean(a >= b))
-                    }
-                    (Value::Number(a), TokenType::Less, Value::Number(b)) => {
-                        Ok(Value::Boolean(a < b))
-                    }
-                    (Value::Number(a), TokenType::LessEqual, Value::Number(b)) => {
-                        Ok(Value::Boolean(a <= b))
-                    }
-                    (_, TokenType::BangEqual, _) => {
-                        Ok(Value::Boolean(!Expr::is_equal(&left, &right)))
-                    }
-                    (_, TokenType::EqualEqual, _) => {
-                        Ok(Value::Boolean(Expr::is_equal(&left, &right)))
-                    }
+        }
+    }
+
+    // the parser only accepts break and continue inside a loop, so only errors get out of a
+    // parsed expression
+    pub fn interpret(&self, interpreter: &mut Interpreter) -> Result<Value, RuntimeError<'err>> {
+        self.evaluate(interpreter).map_err(|unwind| match unwind {
+            Unwind::Error(error) => *error,
+            Unwind::Break(keyword, _) | Unwind::Continue(keyword, _) => RuntimeError {
+                token: Some(keyword.deep_clone()),
+                msg: format!(
+                    "Can't use '{}' outside of a loop",
+                    String::from_utf8_lossy(&keyword.lexeme)
+                ),
+                trace: Vec::new(),
+            },
+        })
+    }
+
+    // every arm is a call of its own, so that nested expressions only stack up small frames
+    fn evaluate(&self, interpreter: &mut Interpreter) -> Evaluation<'token, 'lexeme, 'err> {
+        interpreter.step()?;
+        match self {
+            Self::Literal(val) => Ok(val.clone()),
+            Self::Variable(name) => Ok(Expr::evaluate_variable(name, interpreter)?),
+            Self::Call(callee, paren, arguments, named) => {
+                Expr::evaluate_call(callee, paren, arguments, named, interpreter)
+            }
+            Self::Unary(token, expr) => Expr::evaluate_unary(token, expr, interpreter),
+            Self::Binary(first, operations) => {
+                Expr::evaluate_binary(first, operations, interpreter)
+            }
+            Self::Grouping(expr) => expr.evaluate(interpreter),
+            Self::Ternary(cond, then_expr, else_expr) => {
+                Expr::evaluate_ternary(cond, then_expr, else_expr, interpreter)
+            }
+            Self::Comma(exprs) => Expr::evaluate_comma(exprs, interpreter),
+            Self::Interpolation(parts) => Expr::evaluate_interpolation(parts, interpreter),
+            Self::List(elements) => Expr::evaluate_list(elements, interpreter),
+            Self::Tuple(elements, paren) => Expr::evaluate_tuple(elements, paren, interpreter),
+            Self::Map(entries) => Expr::evaluate_map(entries, interpreter),
+            Self::Index(object, bracket, index) => {
+                Expr::evaluate_index(object, bracket, index, interpreter)
+            }
+            Self::Get(object, name) => Expr::evaluate_get(object, name, interpreter),
+            Self::Slice(object, bracket, start, end) => {
+                Expr::evaluate_slice(object, bracket, start, end, interpreter)
+            }
+            Self::Assign(target, operator, value) => {
+                Expr::evaluate_assign(target, operator, value, interpreter)
+            }
+            Self::Postfix(target, operator) => {
+                Expr::evaluate_postfix(target, operator, interpreter)
+            }
+            Self::Match(scrutinee, keyword, arms) => {
+                Expr::evaluate_match(scrutinee, keyword, arms, interpreter)
+            }
+            Self::For(label, name, keyword, iterable, body) => {
+                Expr::evaluate_for(*label, name, keyword, iterable, body, interpreter)
+            }
+            Self::Break(keyword, label) => Err(Unwind::Break(keyword, *label)),
+            Self::Continue(keyword, label) => Err(Unwind::Continue(keyword, *label)),
+            Self::Throw(keyword, value) => Expr::evaluate_throw(keyword, value, interpreter),
+            Self::Try(body, catch, finally) => {
+                Expr::evaluate_try(body, catch.as_ref(), finally.as_deref(), interpreter)
+            }
+            Self::Function(function) => Expr::evaluate_function(function, interpreter),
+        }
+    }
+
+    fn evaluate_variable(
+        name: &Token<'lexeme>,
+        interpreter: &mut Interpreter,
+    ) -> Result<Value, RuntimeError<'err>> {
+        let name_str = String::from_utf8_lossy(&name.lexeme);
+        match interpreter.lookup(&name_str) {
+            Some(value) => Ok(value),
+            None => Err(RuntimeError {
+                token: Some(name.deep_clone()),
+                msg: format!("Undefined variable '{}'", name_str),
+                trace: Vec::new(),
+            }),
+        }
+    }
+
+    fn evaluate_call(
+        callee: &Expr<'token, 'lexeme>,
+        paren: &Token<'lexeme>,
+        arguments: &[Expr<'token, 'lexeme>],
+        named: &[(&'token Token<'lexeme>, Expr<'token, 'lexeme>)],
+        interpreter: &mut Interpreter,
+    ) -> Evaluation<'token, 'lexeme, 'err> {
+        interpreter.enter_call(paren)?;
+        let value = Expr::call_callee(callee, paren, arguments, named, interpreter);
+        interpreter.exit_call();
+        if value.is_err() {
+            interpreter.check_interrupted(paren)?;
+        }
+        let value = value?;
+        interpreter.check_size(&value, Some(paren))?;
+        Ok(value)
+    }
+
+    fn evaluate_unary(
+        token: &Token<'lexeme>,
+        expr: &Expr<'token, 'lexeme>,
+        interpreter: &mut Interpreter,
+    ) -> Evaluation<'token, 'lexeme, 'err> {
+        let right = expr.evaluate(interpreter)?;
+        match (token.token_type, &right) {
+            (TokenType::Minus, Value::Number(n)) => Ok(Value::Number(-n)),
+            (TokenType::Bang, Value::Boolean(_)) => Ok(Value::Boolean(!Expr::is_truthy(&right))),
+            _ => Err(RuntimeError {
+                token: Some(token.deep_clone()),
+                msg: "Invalid unary expression".into(),
+                trace: Vec::new(),
+            }
+            .into()),
+        }
+    }
+
+    fn evaluate_binary(
+        first: &Expr<'token, 'lexeme>,
+        operations: &[(&'token Token<'lexeme>, Expr<'token, 'lexeme>)],
+        interpreter: &mut Interpreter,
+    ) -> Evaluation<'token, 'lexeme, 'err> {
+        let mut left = first.evaluate(interpreter)?;
+        for (i, (token, r_expr)) in operations.iter().enumerate() {
+            // every operation after the first counts as a step of its own
+            if i > 0 {
+                interpreter.step()?;
+            }
+            let right = r_expr.evaluate(interpreter)?;
+            left = Expr::binary(&left, token, &right)?;
+            interpreter.check_size(&left, Some(token))?;
+        }
+        Ok(left)
+    }
+
+    // only the selected branch is evaluated
+    fn evaluate_ternary(
+        cond: &Expr<'token, 'lexeme>,
+        then_expr: &Expr<'token, 'lexeme>,
+        else_expr: &Expr<'token, 'lexeme>,
+        interpreter: &mut Interpreter,
+    ) -> Evaluation<'token, 'lexeme, 'err> {
+        if Expr::is_truthy(&cond.evaluate(interpreter)?) {
+            then_expr.evaluate(interpreter)
+        } else {
+            else_expr.evaluate(interpreter)
+        }
+    }
+
+    fn evaluate_comma(
+        exprs: &[Expr<'token, 'lexeme>],
+        interpreter: &mut Interpreter,
+    ) -> Evaluation<'token, 'lexeme, 'err> {
+        let mut value = Value::Nil;
+        for expr in exprs {
+            value = expr.evaluate(interpreter)?;
+        }
+        Ok(value)
+    }
+
+    fn evaluate_interpolation(
+        parts: &[Expr<'token, 'lexeme>],
+        interpreter: &mut Interpreter,
+    ) -> Evaluation<'token, 'lexeme, 'err> {
+        let mut string = String::new();
+        for part in parts {
+            string += &part.evaluate(interpreter)?.to_string();
+        }
+        let value = Value::String(string);
+        interpreter.check_size(&value, None)?;
+        Ok(value)
+    }
+
+    fn evaluate_list(
+        elements: &[Expr<'token, 'lexeme>],
+        interpreter: &mut Interpreter,
+    ) -> Evaluation<'token, 'lexeme, 'err> {
+        let mut values = Vec::with_capacity(elements.len());
+        for element in elements {
+            values.push(element.evaluate(interpreter)?);
+        }
+        let value = Value::List(Shared::new(Lock::new(values)));
+        interpreter.check_size(&value, None)?;
+        Ok(value)
+    }
+
+    fn evaluate_tuple(
+        elements: &[Expr<'token, 'lexeme>],
+        paren: &Token<'lexeme>,
+        interpreter: &mut Interpreter,
+    ) -> Evaluation<'token, 'lexeme, 'err> {
+        let mut values = Vec::with_capacity(elements.len());
+        for element in elements {
+            values.push(element.evaluate(interpreter)?);
+        }
+        let value = Value::Tuple(values.into());
+        interpreter.check_size(&value, Some(paren))?;
+        Ok(value)
+    }
+
+    fn evaluate_map(
+        entries: &[(
+            Expr<'token, 'lexeme>,
+            &'token Token<'lexeme>,
+            Expr<'token, 'lexeme>,
+        )],
+        interpreter: &mut Interpreter,
+    ) -> Evaluation<'token, 'lexeme, 'err> {
+        let mut map = Map::new();
+        for (key, colon, value) in entries {
+            let key = key.evaluate(interpreter)?;
+            let value = value.evaluate(interpreter)?;
+            map.insert(key, value).map_err(|msg: &str| RuntimeError {
+                token: Some(colon.deep_clone()),
+                msg: msg.into(),
+                trace: Vec::new(),
+            })?;
+        }
+        let value = Value::Map(Shared::new(Lock::new(map)));
+        interpreter.check_size(&value, None)?;
+        Ok(value)
+    }
+
+    fn evaluate_index(
+        object: &Expr<'token, 'lexeme>,
+        bracket: &Token<'lexeme>,
+        index: &Expr<'token, 'lexeme>,
+        interpreter: &mut Interpreter,
+    ) -> Evaluation<'token, 'lexeme, 'err> {
+        let object = object.evaluate(interpreter)?;
+        let index = index.evaluate(interpreter)?;
+        Ok(Expr::get_index(&object, &index, bracket)?)
+    }
+
+    fn evaluate_get(
+        object: &Expr<'token, 'lexeme>,
+        name: &Token<'lexeme>,
+        interpreter: &mut Interpreter,
+    ) -> Evaluation<'token, 'lexeme, 'err> {
+        let object = object.evaluate(interpreter)?;
+        Ok(Expr::get_property(&object, name)?)
+    }
+
+    fn evaluate_slice(
+        object: &Expr<'token, 'lexeme>,
+        bracket: &Token<'lexeme>,
+        start: &Option<Box<Expr<'token, 'lexeme>>>,
+        end: &Option<Box<Expr<'token, 'lexeme>>>,
+        interpreter: &mut Interpreter,
+    ) -> Evaluation<'token, 'lexeme, 'err> {
+        let object = object.evaluate(interpreter)?;
+        let start = match start {
+            Some(start) => Some(start.evaluate(interpreter)?),
+            None => None,
+        };
+        let end = match end {
+            Some(end) => Some(end.evaluate(interpreter)?),
+            None => None,
+        };
+        Ok(Expr::slice(&object, start.as_ref(), end.as_ref(), bracket)?)
+    }
+
+    fn evaluate_assign(
+        target: &Target<'token, 'lexeme>,
+        operator: &'token Token<'lexeme>,
+        value: &Expr<'token, 'lexeme>,
+        interpreter: &mut Interpreter,
+    ) -> Evaluation<'token, 'lexeme, 'err> {
+        let place = Expr::evaluate_target(target, interpreter)?;
+        let value = value.evaluate(interpreter)?;
+        let value = if operator.token_type == TokenType::Equal {
+            value
+        } else {
+            Expr::binary(&Expr::get_place(&place, interpreter)?, operator, &value)?
+        };
+        interpreter.check_size(&value, Some(operator))?;
+        // assigning to a new map key grows the map
+        let object = match &place {
+            Place::Index(object, _, _) => Some(object.clone()),
+            _ => None,
+        };
+        Expr::set_place(place, value.clone(), interpreter)?;
+        if let Some(object) = object {
+            interpreter.check_size(&object, Some(operator))?;
+        }
+        Ok(value)
+    }
 
-                    // error cases
-                    (_, TokenType::Plus, _) => Err(RuntimeError {
-                        token: token.deep_clone(),
-                        msg:
-                            "Invalid binary expression: Operands must be two numbers or two strings",
-                    }),
-                    (_, TokenType::Minus, _)
-                    | (_, TokenType::Star, _)
-                    | (_, TokenType::Slash, _)
-                    | (_, TokenType::Greater, _)
-                    | (_, TokenType::GreaterEqual, _)
-                    | (_, TokenType::Less, _)
-                    | (_, TokenType::LessEqual, _) => Err(RuntimeError {
-                        token: token.deep_clone(),
-                        msg: "Invalid binary expression: Operands must be two numbers",
-                    }),
-                    _ => Err(RuntimeError {
-                        token: token.deep_clone(),
-                        msg: "Invalid binary expression: reason unknown",
-                    }),
+    fn evaluate_postfix(
+        target: &Target<'token, 'lexeme>,
+        operator: &Token<'lexeme>,
+        interpreter: &mut Interpreter,
+    ) -> Evaluation<'token, 'lexeme, 'err> {
+        let place = Expr::evaluate_target(target, interpreter)?;
+        let old = Expr::get_place(&place, interpreter)?;
+        let new = Expr::binary(&old, operator, &Value::Number(1.0))?;
+        Expr::set_place(place, new, interpreter)?;
+        Ok(old)
+    }
+
+    // the first arm whose pattern matches and whose guard (if any) is truthy is evaluated
+    fn evaluate_match(
+        scrutinee: &Expr<'token, 'lexeme>,
+        keyword: &Token<'lexeme>,
+        arms: &[MatchArm<'token, 'lexeme>],
+        interpreter: &mut Interpreter,
+    ) -> Evaluation<'token, 'lexeme, 'err> {
+        let value = scrutinee.evaluate(interpreter)?;
+        for arm in arms {
+            if !arm.pattern.matches(&value) {
+                continue;
+            }
+            let guard_passed = match &arm.guard {
+                Some(guard) => Expr::is_truthy(&guard.evaluate(interpreter)?),
+                None => true,
+            };
+            if guard_passed {
+                return arm.body.evaluate(interpreter);
+            }
+        }
+        Err(RuntimeError {
+            token: Some(keyword.deep_clone()),
+            msg: "Non-exhaustive match: no arm matches the value".into(),
+            trace: Vec::new(),
+        }
+        .into())
+    }
+
+    // errors from iterating, e.g. from a host object's next(), are reported at the 'in' token
+    fn evaluate_for(
+        label: Option<&Token<'lexeme>>,
+        name: &Token<'lexeme>,
+        keyword: &Token<'lexeme>,
+        iterable: &Expr<'token, 'lexeme>,
+        body: &Expr<'token, 'lexeme>,
+        interpreter: &mut Interpreter,
+    ) -> Evaluation<'token, 'lexeme, 'err> {
+        let at_keyword = |error: RuntimeError<'static>| RuntimeError {
+            token: error.token.or_else(|| Some(keyword.deep_clone())),
+            msg: error.msg,
+            trace: error.trace,
+        };
+        let iterable = iterable.evaluate(interpreter)?;
+        let mut iteration = Iteration::new(&iterable).map_err(at_keyword)?;
+        let name = String::from_utf8_lossy(&name.lexeme);
+        while let Some(value) = iteration.next().map_err(at_keyword)? {
+            interpreter.assign(&name, value);
+            match body.evaluate(interpreter) {
+                Err(Unwind::Break(_, target)) if Expr::is_loop(label, target) => break,
+                Err(Unwind::Continue(_, target)) if Expr::is_loop(label, target) => continue,
+                Err(unwind) => return Err(unwind),
+                Ok(_) => (),
+            }
+        }
+        Ok(Value::Nil)
+    }
+
+    // a thrown value travels as a runtime error, see Interpreter::throw
+    fn evaluate_throw(
+        keyword: &Token<'lexeme>,
+        value: &Expr<'token, 'lexeme>,
+        interpreter: &mut Interpreter,
+    ) -> Evaluation<'token, 'lexeme, 'err> {
+        let value = value.evaluate(interpreter)?;
+        Err(interpreter.throw(keyword, value).into())
+    }
+
+    // the catch block gets the error and its value replaces the body's; the finally block runs
+    // however the body and the catch block end, and only an error of its own replaces theirs
+    // out of line, as its locals would otherwise grow the frame of every evaluate call
+    #[inline(never)]
+    #[allow(clippy::type_complexity)]
+    fn evaluate_try(
+        body: &Expr<'token, 'lexeme>,
+        catch: Option<&(&Token<'lexeme>, Box<Expr<'token, 'lexeme>>)>,
+        finally: Option<&Expr<'token, 'lexeme>>,
+        interpreter: &mut Interpreter,
+    ) -> Evaluation<'token, 'lexeme, 'err> {
+        let value = match (body.evaluate(interpreter), catch) {
+            (Err(Unwind::Error(error)), Some((name, handler))) => match interpreter.catch(*error) {
+                Ok(caught) => {
+                    interpreter.assign(&String::from_utf8_lossy(&name.lexeme), caught);
+                    handler.evaluate(interpreter)
                 }
+                Err(error) => Err(error.into()),
+            },
+            (value, _) => value,
+        };
+        if let Some(finally) = finally {
+            interpreter.finally(|interpreter| finally.evaluate(interpreter))?;
+        }
+        value
+    }
+
+    // functions are only created while a compiled Program runs, since a closure keeps the program
+    // it was parsed from alive
+    fn evaluate_function(
+        function: &Function<'token, 'lexeme>,
+        interpreter: &mut Interpreter,
+    ) -> Evaluation<'token, 'lexeme, 'err> {
+        match interpreter.closure(function.id) {
+            Some(closure) => Ok(Value::Function(Shared::new(closure))),
+            None => Err(RuntimeError {
+                token: Some(function.token.deep_clone()),
+                msg: "Functions can only be created by a compiled Program, see Interpreter::run"
+                    .into(),
+                trace: Vec::new(),
             }
-            Self::Grouping(expr) => expr.interpret(),
+            .into()),
+        }
+    }
+
+    // whether a break or continue with the target label belongs to the loop with this label
+    fn is_loop(label: Option<&Token<'_>>, target: Option<&Token<'_>>) -> bool {
+        match (label, target) {
+            (_, None) => true,
+            (Some(label), Some(target)) => label.lexeme == target.lexeme,
+            (None, Some(_)) => false,
         }
     }
 
+    fn call_callee(
+        callee_expr: &Expr<'token, 'lexeme>,
+        paren: &Token<'lexeme>,
+        arguments: &[Expr<'token, 'lexeme>],
+        named: &[(&'token Token<'lexeme>, Expr<'token, 'lexeme>)],
+        interpreter: &mut Interpreter,
+    ) -> Evaluation<'token, 'lexeme, 'err> {
+        // obj.method(...) on a host object is a method call rather than a call of the property
+        let (callee, method) = match callee_expr {
+            Self::Get(object, name) => match object.evaluate(interpreter)? {
+                object @ Value::HostObject(_) => (object, Some(*name)),
+                object => (Expr::get_property(&object, name)?, None),
+            },
+            callee => (callee.evaluate(interpreter)?, None),
+        };
+        let (values, named_values) = Expr::evaluate_arguments(arguments, named, interpreter)?;
+        match &callee {
+            Value::Function(closure) => Ok(Expr::call_function(
+                closure,
+                callee_expr,
+                paren,
+                values,
+                named_values,
+                interpreter,
+            )?),
+            _ => Ok(Expr::call_native(
+                &callee,
+                callee_expr,
+                method,
+                paren,
+                &values,
+                &named_values,
+            )?),
+        }
+    }
+
+    // out of line, like the other steps of a call that are done before a function's body runs,
+    // so that the frames which stay on the stack while it runs are small
+    #[inline(never)]
+    #[allow(clippy::type_complexity)]
+    fn evaluate_arguments(
+        arguments: &[Expr<'token, 'lexeme>],
+        named: &[(&'token Token<'lexeme>, Expr<'token, 'lexeme>)],
+        interpreter: &mut Interpreter,
+    ) -> Result<(Vec<Value>, Vec<(&'token Token<'lexeme>, Value)>), Unwind<'token, 'lexeme, 'err>>
+    {
+        let mut values = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            values.push(argument.evaluate(interpreter)?);
+        }
+        let mut named_values = Vec::with_capacity(named.len());
+        for (name, argument) in named {
+            named_values.push((*name, argument.evaluate(interpreter)?));
+        }
+        Ok((values, named_values))
+    }
+
+    // natives and methods of host objects, which only take positional arguments
+    #[inline(never)]
+    fn call_native(
+        callee: &Value,
+        callee_expr: &Expr<'_, '_>,
+        method: Option<&Token<'_>>,
+        paren: &Token<'_>,
+        arguments: &[Value],
+        named: &[(&Token<'_>, Value)],
+    ) -> Result<Value, RuntimeError<'err>> {
+        if let Some((name, _)) = named.first() {
+            return Err(RuntimeError {
+                token: Some(name.deep_clone()),
+                msg: format!(
+                    "Only functions written in Lox take named arguments, got {}",
+                    callee.type_name()
+                ),
+                trace: Vec::new(),
+            });
+        }
+        match (callee, method) {
+            (Value::HostObject(host), Some(name)) => {
+                let name_str = String::from_utf8_lossy(&name.lexeme);
+                let result = host.call_method(&name_str, arguments);
+                result.map_err(|error| RuntimeError {
+                    token: error.token.or_else(|| Some(name.deep_clone())),
+                    msg: error.msg,
+                    trace: error.trace,
+                })
+            }
+            _ => Expr::call(callee, paren, arguments),
+        }
+        .map_err(|error| error.unwind_call(callee_expr.callee_name(), paren))
+    }
+
+    // how a call names the function it calls in a stack trace: f for f(), a.b for a.b()
+    fn callee_name(&self) -> String {
+        match self {
+            Self::Variable(name) => String::from_utf8_lossy(&name.lexeme).into_owned(),
+            Self::Get(object, name) => {
+                object.callee_name() + "." + &String::from_utf8_lossy(&name.lexeme)
+            }
+            _ => "<anonymous>".to_owned(),
+        }
+    }
+
+    // the parameters are the first variables of a new scope inside the one the function was
+    // created in, a break or continue can't leave the body as the parser doesn't allow them there
+    fn call_function(
+        closure: &Closure,
+        callee_expr: &Expr<'_, '_>,
+        paren: &Token<'_>,
+        arguments: Vec<Value>,
+        named: Vec<(&Token<'_>, Value)>,
+        interpreter: &mut Interpreter,
+    ) -> Result<Value, RuntimeError<'err>> {
+        let function = closure.function();
+        let (variables, defaults) = Expr::bind_arguments(closure, paren, arguments, named)?;
+        let scope = Scope::new(variables, closure.scope.clone());
+        interpreter
+            .call_in(closure, scope, |interpreter| {
+                for (name, default) in defaults {
+                    let value = default.interpret(interpreter)?;
+                    interpreter.declare(name, value);
+                }
+                function.body.interpret(interpreter)
+            })
+            .map_err(|error| error.unwind_call(callee_expr.callee_name(), paren))
+    }
+
+    // positional arguments fill the parameters in order, the rest parameter takes any left over,
+    // then named arguments fill parameters by name; gives the variables of the call and the
+    // parameters left to their defaults
+    // kept apart from call_function so that its locals are gone while the body runs
+    #[inline(never)]
+    fn bind_arguments<'a>(
+        closure: &'a Closure,
+        paren: &Token<'_>,
+        arguments: Vec<Value>,
+        named: Vec<(&Token<'_>, Value)>,
+    ) -> Result<Bindings<'a>, RuntimeError<'err>> {
+        let function = closure.function();
+        let error = |token: &Token<'_>, msg: String| RuntimeError {
+            token: Some(token.deep_clone()),
+            msg: format!("{}{} {}", closure, function.signature(), msg),
+            trace: Vec::new(),
+        };
+        let given = arguments.len() + named.len();
+        let by_name = !named.is_empty();
+        let arity_error = || {
+            error(
+                paren,
+                format!("expects {} but got {}", function.arity(), given),
+            )
+        };
+        let name = |token: &Token<'_>| String::from_utf8_lossy(&token.lexeme).into_owned();
+
+        let mut variables = HashMap::new();
+        let mut arguments = arguments.into_iter();
+        for (param, value) in function.params.iter().zip(&mut arguments) {
+            variables.insert(name(param.name), value);
+        }
+        let extra: Vec<Value> = arguments.collect();
+        match function.rest {
+            Some(rest) => {
+                variables.insert(name(rest), Value::List(Shared::new(Lock::new(extra))));
+            }
+            None if !extra.is_empty() => return Err(arity_error()),
+            None => (),
+        }
+        for (token, value) in named {
+            if !function
+                .params
+                .iter()
+                .any(|param| param.name.lexeme == token.lexeme)
+            {
+                return Err(error(
+                    token,
+                    format!("has no parameter named '{}'", name(token)),
+                ));
+            }
+            if variables.insert(name(token), value).is_some() {
+                return Err(error(
+                    token,
+                    format!("got more than one value for '{}'", name(token)),
+                ));
+            }
+        }
+        let mut defaults = Vec::new();
+        for param in &function.params {
+            match &param.default {
+                _ if variables.contains_key(&name(param.name)) => (),
+                Some(default) => defaults.push((name(param.name), default)),
+                // a missing positional argument is reported as a wrong count unless names were used
+                None if !by_name => return Err(arity_error()),
+                None => {
+                    return Err(error(
+                        paren,
+                        format!("is missing the argument '{}'", name(param.name)),
+                    ))
+                }
+            }
+        }
+        Ok((variables, defaults))
+    }
+
+    fn call(
+        callee: &Value,
+        paren: &Token<'_>,
+        arguments: &[Value],
+    ) -> Result<Value, RuntimeError<'err>> {
+        let Value::NativeFunction(native) = callee else {
+            return Err(RuntimeError {
+                token: Some(paren.deep_clone()),
+                msg: "Can only call functions".into(),
+                trace: Vec::new(),
+            });
+        };
+
+        if arguments.len() != native.arity {
+            return Err(RuntimeError {
+                token: Some(paren.deep_clone()),
+                msg: format!(
+                    "{}() expects {} argument(s) but got {}",
+                    native.name,
+                    native.arity,
+                    arguments.len()
+                ),
+                trace: Vec::new(),
+            });
+        }
+
+        (native.function)(arguments).map_err(|error| RuntimeError {
+            token: error.token.or_else(|| Some(paren.deep_clone())),
+            msg: error.msg,
+            trace: error.trace,
+        })
+    }
+
+    // all subexpressions of the target are evaluated before the assigned value
+    fn evaluate_target(
+        target: &Target<'token, 'lexeme>,
+        interpreter: &mut Interpreter,
+    ) -> Result<Place<'token, 'lexeme>, Unwind<'token, 'lexeme, 'err>> {
+        match target {
+            Target::Variable(name) => Ok(Place::Variable(name)),
+            Target::Index(object, bracket, index) => Ok(Place::Index(
+                object.evaluate(interpreter)?,
+                index.evaluate(interpreter)?,
+                bracket,
+            )),
+            Target::Field(object, name) => Ok(Place::Field(object.evaluate(interpreter)?, name)),
+            Target::Tuple(targets, paren) => {
+                let mut places = Vec::with_capacity(targets.len());
+                for target in targets {
+                    places.push(Expr::evaluate_target(target, interpreter)?);
+                }
+                Ok(Place::Tuple(places, paren))
+            }
+        }
+    }
+
+    fn get_place(
+        place: &Place<'token, 'lexeme>,
+        interpreter: &mut Interpreter,
+    ) -> Result<Value, RuntimeError<'err>> {
+        match place {
+            Place::Variable(name) => Expr::evaluate_variable(name, interpreter),
+            Place::Index(object, index, bracket) => Expr::get_index(object, index, bracket),
+            Place::Field(object, name) => Expr::get_property(object, name),
+            Place::Tuple(places, _) => {
+                let mut values = Vec::with_capacity(places.len());
+                for place in places {
+                    values.push(Expr::get_place(place, interpreter)?);
+                }
+                Ok(Value::Tuple(values.into()))
+            }
+        }
+    }
+
+    fn set_place(
+        place: Place<'token, 'lexeme>,
+        value: Value,
+        interpreter: &mut Interpreter,
+    ) -> Result<(), RuntimeError<'err>> {
+        match place {
+            Place::Variable(name) => {
+                interpreter.assign(&String::from_utf8_lossy(&name.lexeme), value);
+                Ok(())
+            }
+            Place::Index(object, index, bracket) => Expr::set_index(&object, index, value, bracket),
+            Place::Field(object, name) => Expr::set_property(&object, name, value),
+            Place::Tuple(places, paren) => {
+                let error = |msg: &str| RuntimeError {
+                    token: Some(paren.deep_clone()),
+                    msg: msg.into(),
+                    trace: Vec::new(),
+                };
+                let values = match value {
+                    Value::Tuple(tuple) => tuple.to_vec(),
+                    Value::List(list) => list.borrow().clone(),
+                    _ => return Err(error("Only tuples and lists can be unpacked")),
+                };
+                if values.len() != places.len() {
+                    return Err(error(
+                        "Number of values does not match the destructuring pattern",
+                    ));
+                }
+                for (place, value) in places.into_iter().zip(values) {
+                    Expr::set_place(place, value, interpreter)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // compound assignment and increment/decrement operators apply the arithmetic operator they are built on
+    fn binary(left: &Value, token: &Token<'_>, right: &Value) -> Result<Value, RuntimeError<'err>> {
+        let operator = match token.token_type {
+            TokenType::PlusEqual | TokenType::PlusPlus => TokenType::Plus,
+            TokenType::MinusEqual | TokenType::MinusMinus => TokenType::Minus,
+            TokenType::StarEqual => TokenType::Star,
+            TokenType::SlashEqual => TokenType::Slash,
+            token_type => token_type,
+        };
+        match (left, operator, right) {
+            // arithmetic operators
+            (Value::Number(a), TokenType::Minus, Value::Number(b)) => Ok(Value::Number(a - b)),
+            (Value::Number(a), TokenType::Plus, Value::Number(b)) => Ok(Value::Number(a + b)),
+            (Value::String(s1), TokenType::Plus, Value::String(s2)) => {
+                Ok(Value::String(s1.to_owned() + s2))
+            }
+            (Value::Number(a), TokenType::Star, Value::Number(b)) => Ok(Value::Number(a * b)),
+            (Value::Number(a), TokenType::Slash, Value::Number(b)) => Ok(Value::Number(a / b)),
+            // comparison operators
+            (Value::Number(a), TokenType::Greater, Value::Number(b)) => Ok(Value::Boolean(a > b)),
+            (Value::Number(a), TokenType::GreaterEqual, Value::Number(b)) => {
+                Ok(Value::Boolean(a >= b))
+            }
+            (Value::Number(a), TokenType::Less, Value::Number(b)) => Ok(Value::Boolean(a < b)),
+            (Value::Number(a), TokenType::LessEqual, Value::Number(b)) => {
+                Ok(Value::Boolean(a <= b))
+            }
+            (_, TokenType::BangEqual, _) => Ok(Value::Boolean(!Expr::is_equal(left, right))),
+            (_, TokenType::EqualEqual, _) => Ok(Value::Boolean(Expr::is_equal(left, right))),
+
+            // error cases
+            (_, TokenType::Plus, _) => Err(RuntimeError {
+                token: Some(token.deep_clone()),
+                msg: "Invalid binary expression: Operands must be two numbers or two strings"
+                    .into(),
+                trace: Vec::new(),
+            }),
+            (_, TokenType::Minus, _)
+            | (_, TokenType::Star, _)
+            | (_, TokenType::Slash, _)
+            | (_, TokenType::Greater, _)
+            | (_, TokenType::GreaterEqual, _)
+            | (_, TokenType::Less, _)
+            | (_, TokenType::LessEqual, _) => Err(RuntimeError {
+                token: Some(token.deep_clone()),
+                msg: "Invalid binary expression: Operands must be two numbers".into(),
+                trace: Vec::new(),
+            }),
+            _ => Err(RuntimeError {
+                token: Some(token.deep_clone()),
+                msg: "Invalid binary expression: reason unknown".into(),
+                trace: Vec::new(),
+            }),
+        }
+    }
+
+    fn get_index(
+        object: &Value,
+        index: &Value,
+        bracket: &Token<'_>,
+    ) -> Result<Value, RuntimeError<'err>> {
+        let error = |msg: &str| RuntimeError {
+            token: Some(bracket.deep_clone()),
+            msg: msg.into(),
+            trace: Vec::new(),
+        };
+        match object {
+            Value::List(list) => {
+                let list = list.borrow();
+                Ok(list[Expr::list_index(list.len(), index, bracket)?].clone())
+            }
+            Value::Tuple(tuple) => {
+                Ok(tuple[Expr::list_index(tuple.len(), index, bracket)?].clone())
+            }
+            Value::Map(map) => match map.borrow().get(index).map_err(error)? {
+                Some(value) => Ok(value.clone()),
+                None => Err(error("Key not found in map")),
+            },
+            _ => Err(error("Only lists, tuples and maps can be indexed")),
+        }
+    }
+
+    // assigning to a missing map key inserts it
+    fn set_index(
+        object: &Value,
+        index: Value,
+        value: Value,
+        bracket: &Token<'_>,
+    ) -> Result<(), RuntimeError<'err>> {
+        let error = |msg: &str| RuntimeError {
+            token: Some(bracket.deep_clone()),
+            msg: msg.into(),
+            trace: Vec::new(),
+        };
+        match object {
+            Value::List(list) => {
+                let mut list = list.borrow_mut();
+                let i = Expr::list_index(list.len(), &index, bracket)?;
+                list[i] = value;
+                Ok(())
+            }
+            Value::Map(map) => map.borrow_mut().insert(index, value).map_err(error),
+            Value::Tuple(_) => Err(error("Tuples are immutable")),
+            _ => Err(error("Only lists and maps can be assigned to by index")),
+        }
+    }
+
+    // errors raised by the host object are reported at the property name
+    fn get_property(object: &Value, name: &Token<'_>) -> Result<Value, RuntimeError<'err>> {
+        let Value::HostObject(host) = object else {
+            return Err(RuntimeError {
+                token: Some(name.deep_clone()),
+                msg: format!("Only objects have properties, got {}", object.type_name()),
+                trace: Vec::new(),
+            });
+        };
+        let name_str = String::from_utf8_lossy(&name.lexeme);
+        let value = host.get(&name_str);
+        value.map_err(|error| RuntimeError {
+            token: error.token.or_else(|| Some(name.deep_clone())),
+            msg: error.msg,
+            trace: error.trace,
+        })
+    }
+
+    fn set_property(
+        object: &Value,
+        name: &Token<'_>,
+        value: Value,
+    ) -> Result<(), RuntimeError<'err>> {
+        let Value::HostObject(host) = object else {
+            return Err(RuntimeError {
+                token: Some(name.deep_clone()),
+                msg: format!("Only objects have properties, got {}", object.type_name()),
+                trace: Vec::new(),
+            });
+        };
+        let name_str = String::from_utf8_lossy(&name.lexeme);
+        let result = host.set(&name_str, value);
+        result.map_err(|error| RuntimeError {
+            token: error.token.or_else(|| Some(name.deep_clone())),
+            msg: error.msg,
+            trace: error.trace,
+        })
+    }
+
+    fn slice(
+        object: &Value,
+        start: Option<&Value>,
+        end: Option<&Value>,
+        bracket: &Token<'_>,
+    ) -> Result<Value, RuntimeError<'err>> {
+        match object {
+            Value::List(list) => {
+                let list = list.borrow();
+                let range = Expr::slice_range(list.len(), start, end, bracket)?;
+                Ok(Value::List(Shared::new(Lock::new(list[range].to_vec()))))
+            }
+            Value::Tuple(tuple) => {
+                let range = Expr::slice_range(tuple.len(), start, end, bracket)?;
+                Ok(Value::Tuple(tuple[range].into()))
+            }
+            _ => Err(RuntimeError {
+                token: Some(bracket.deep_clone()),
+                msg: "Only lists and tuples can be sliced".into(),
+                trace: Vec::new(),
+            }),
+        }
+    }
+
+    fn slice_range(
+        len: usize,
+        start: Option<&Value>,
+        end: Option<&Value>,
+        bracket: &Token<'_>,
+    ) -> Result<Range<usize>, RuntimeError<'err>> {
+        let start = Expr::slice_bound(len, start, 0, bracket)?;
+        let end = Expr::slice_bound(len, end, len, bracket)?;
+        Ok(start..end.max(start))
+    }
+
+    fn as_integer(value: &Value, bracket: &Token<'_>) -> Result<i64, RuntimeError<'err>> {
+        match value {
+            Value::Number(n) if n.fract() == 0.0 => Ok(*n as i64),
+            _ => Err(RuntimeError {
+                token: Some(bracket.deep_clone()),
+                msg: "List index must be an integer".into(),
+                trace: Vec::new(),
+            }),
+        }
+    }
+
+    // negative indices count from the end of the list
+    fn list_index(
+        len: usize,
+        index: &Value,
+        bracket: &Token<'_>,
+    ) -> Result<usize, RuntimeError<'err>> {
+        let index = Expr::as_integer(index, bracket)?;
+        let index = if index < 0 { index + len as i64 } else { index };
+        if 0 <= index && index < len as i64 {
+            Ok(index as usize)
+        } else {
+            Err(RuntimeError {
+                token: Some(bracket.deep_clone()),
+                msg: "List index out of bounds".into(),
+                trace: Vec::new(),
+            })
+        }
+    }
+
+    // slice bounds may be negative and are clamped to the list, a missing bound takes its default
+    fn slice_bound(
+        len: usize,
+        bound: Option<&Value>,
+        default: usize,
+        bracket: &Token<'_>,
+    ) -> Result<usize, RuntimeError<'err>> {
+        let Some(bound) = bound else {
+            return Ok(default);
+        };
+        let bound = Expr::as_integer(bound, bracket)?;
+        let bound = if bound < 0 { bound + len as i64 } else { bound };
+        Ok(bound.clamp(0, len as i64) as usize)
+    }
+
     fn is_truthy(val: &Value) -> bool {
         match val {
             Value::Nil => false,
@@ -126,11 +1565,56 @@ impl<'token, 'lexeme, 'err> Expr<'token, 'lexeme> {
     }
 
     fn is_equal(val1: &Value, val2: &Value) -> bool {
+        Expr::is_equal_within(val1, val2, None)
+    }
+
+    // pairs of lists or maps already being compared further up are assumed equal,
+    // so comparing values that contain themselves ends
+    fn is_equal_within(
+        val1: &Value,
+        val2: &Value,
+        comparing: Option<&Ancestors<(*const (), *const ())>>,
+    ) -> bool {
+        let frame = match (val1.container_id(), val2.container_id()) {
+            (Some(id1), Some(id2)) => {
+                if Ancestors::contains(comparing, (id1, id2)) {
+                    return true;
+                }
+                Some(Ancestors::new((id1, id2), comparing))
+            }
+            _ => None,
+        };
+        let comparing = frame.as_ref().or(comparing);
+        let equal = |a: &Value, b: &Value| Expr::is_equal_within(a, b, comparing);
+
         match (val1, val2) {
             (Value::Nil, Value::Nil) => true,
             (Value::Number(n1), Value::Number(n2)) => n1 == n2,
             (Value::String(s1), Value::String(s2)) => s1 == s2,
             (Value::Boolean(b1), Value::Boolean(b2)) => b1 == b2,
+            (Value::Tuple(t1), Value::Tuple(t2)) => {
+                t1.len() == t2.len() && t1.iter().zip(t2.iter()).all(|(a, b)| equal(a, b))
+            }
+            (Value::List(l1), Value::List(l2)) => {
+                Shared::ptr_eq(l1, l2) || {
+                    let (l1, l2) = (l1.borrow(), l2.borrow());
+                    l1.len() == l2.len() && l1.iter().zip(l2.iter()).all(|(a, b)| equal(a, b))
+                }
+            }
+            (Value::NativeFunction(f1), Value::NativeFunction(f2)) => Shared::ptr_eq(f1, f2),
+            (Value::Function(f1), Value::Function(f2)) => Shared::ptr_eq(f1, f2),
+            (Value::HostObject(o1), Value::HostObject(o2)) => Shared::ptr_eq(o1, o2),
+            // maps are equal when they hold equal values under the same keys, regardless of order
+            (Value::Map(m1), Value::Map(m2)) => {
+                Shared::ptr_eq(m1, m2) || {
+                    let (m1, m2) = (m1.borrow(), m2.borrow());
+                    m1.len() == m2.len()
+                        && m1.iter().all(|(key, v1)| match m2.get(key) {
+                            Ok(Some(v2)) => equal(v1, v2),
+                            _ => false,
+                        })
+                }
+            }
             _ => false,
         }
     }
@@ -1,11 +1,489 @@
-use crate::error::LoxError;
+use crate::capabilities::Capabilities;
+use crate::error::{LoxError, RuntimeError};
 use crate::expr::Expr;
-use crate::value::Value;
+use crate::natives;
+use crate::program::Program;
+use crate::streams::Streams;
+use crate::sync::{Lock, Shared, SyncLock};
+use crate::token::Token;
+use crate::value::{Closure, Map, NativeFunction, Value};
 
-pub struct Interpreter {}
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// how often (in evaluation steps) the clock is read when a timeout is set
+const TIMEOUT_CHECK_INTERVAL: u64 = 256;
+
+// stack that nested calls of script functions may take up before a call is refused, whatever
+// max_call_depth allows; measured in a debug build, a call takes about 12KB (a release build about
+// 2.5KB) and the deepest expression the parser accepts takes at most about 550KB more to evaluate
+// inside the last call, so the total stays below the 2MiB stack of a thread started with
+// std::thread::spawn
+const MAX_CALL_STACK: usize = 768 * 1024;
+
+// bounds for running untrusted scripts, None means unlimited
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Limits {
+    // expressions evaluated by a single call to interpret
+    pub max_steps: Option<u64>,
+    // calls nested inside each other, including calls in the arguments of other calls
+    pub max_call_depth: Option<usize>,
+    // bytes of a string or elements of a list, tuple or map created by the script
+    pub max_value_size: Option<usize>,
+}
+
+// lets another thread stop a running script, see Interpreter::cancel_handle
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    // the running evaluation ends with LoxError::Cancelled, or the next one does when nothing runs
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    // a cancellation is consumed by the evaluation it stops
+    fn take(&self) -> bool {
+        self.0.swap(false, Ordering::Relaxed)
+    }
+}
+
+// whether the evaluation running a native has been cancelled or has run out of time
+#[derive(Clone)]
+pub(crate) struct Interrupt {
+    cancel: CancelHandle,
+    deadline: Shared<SyncLock<Option<Instant>>>,
+}
+
+impl Interrupt {
+    pub(crate) fn is_interrupted(&self) -> bool {
+        let deadline = *self.deadline.borrow();
+        self.cancel.is_cancelled() || deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+// why evaluation was stopped early, reported as its own LoxError variant
+#[derive(Debug, Clone, Copy)]
+enum Halt {
+    Steps,
+    CallDepth,
+    Memory,
+    Cancelled,
+    Timeout,
+}
+
+// the variables of a call of a script function: its parameters and the variables it assigns to first
+// functions look a name up through the scopes they were created in, then the globals
+pub(crate) struct Scope {
+    variables: HashMap<String, Value>,
+    enclosing: Option<Shared<Lock<Scope>>>,
+}
+
+impl Scope {
+    pub(crate) fn new(
+        variables: HashMap<String, Value>,
+        enclosing: Option<Shared<Lock<Scope>>>,
+    ) -> Self {
+        Self {
+            variables,
+            enclosing,
+        }
+    }
+}
+
+pub struct Interpreter {
+    globals: HashMap<String, Value>,
+    // the scope of the running function call, None at the top level
+    scope: Option<Shared<Lock<Scope>>>,
+    // the program being run, which function expressions are created from
+    program: Option<Program>,
+    streams: Streams,
+    capabilities: Shared<SyncLock<Capabilities>>,
+    limits: Limits,
+    steps: u64,
+    call_depth: usize,
+    // address on the stack where the current evaluation started
+    stack_base: usize,
+    halt: Option<Halt>,
+    // the value of the throw whose error is unwinding, errors themselves only carry a message
+    thrown: Option<Value>,
+    cancel: CancelHandle,
+    timeout: Option<Duration>,
+    deadline: Shared<SyncLock<Option<Instant>>>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl<'token, 'lexeme, 'err> Interpreter {
-    pub fn interpret(expr: Expr<'token, 'lexeme>) -> Result<Value, LoxError<'err>> {
-        Ok(expr.interpret()?)
+    // comes with the standard native functions already defined
+    // the only capability allowed is the clock, see set_capabilities
+    pub fn new() -> Self {
+        let mut interpreter = Self {
+            globals: HashMap::new(),
+            scope: None,
+            program: None,
+            streams: Streams::default(),
+            capabilities: Shared::new(SyncLock::new(Capabilities::none().allow_clock())),
+            limits: Limits::default(),
+            steps: 0,
+            call_depth: 0,
+            stack_base: 0,
+            halt: None,
+            thrown: None,
+            cancel: CancelHandle::default(),
+            timeout: None,
+            deadline: Shared::new(SyncLock::new(None)),
+        };
+        natives::define_standard_natives(&mut interpreter);
+        interpreter
+    }
+
+    // exposes a Rust function to scripts as a global, replacing any global with the same name
+    // errors created with RuntimeError::new are reported at the script's call site
+    pub fn define_native<F>(&mut self, name: &str, arity: usize, function: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, RuntimeError<'static>> + Send + Sync + 'static,
+    {
+        let native = NativeFunction {
+            name: name.to_owned(),
+            arity,
+            function: Box::new(function),
+        };
+        self.define(name, Value::NativeFunction(Shared::new(native)));
+    }
+
+    // evaluates a compiled program, whose function expressions can only be evaluated this way
+    pub fn run(&mut self, program: &Program) -> Result<Value, LoxError<'err>> {
+        let enclosing = self.program.replace(program.clone());
+        let value = self.interpret(program.expr());
+        self.program = enclosing;
+        value
+    }
+
+    // the step budget and the timeout apply to each call separately
+    // a cancel sent before the call starts stops it at its first step
+    pub fn interpret(&mut self, expr: &Expr<'token, 'lexeme>) -> Result<Value, LoxError<'err>> {
+        self.steps = 0;
+        self.call_depth = 0;
+        self.stack_base = stack_address();
+        self.halt = None;
+        self.thrown = None;
+        *self.deadline.borrow_mut() = self.timeout.map(|timeout| Instant::now() + timeout);
+        let value = expr.interpret(self);
+        // a cancel that came after the last check has nothing left to stop, so it isn't kept
+        // around for the next call
+        self.cancel.take();
+        self.thrown = None;
+        value.map_err(|error| match self.halt.take() {
+            Some(Halt::Steps) => LoxError::StepLimit(error),
+            Some(Halt::CallDepth) => LoxError::CallDepthLimit(error),
+            Some(Halt::Memory) => LoxError::MemoryLimit(error),
+            Some(Halt::Cancelled) => LoxError::Cancelled(error),
+            Some(Halt::Timeout) => LoxError::Timeout(error),
+            None => LoxError::Runtime(error),
+        })
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    // can be sent to another thread to cancel evaluations of this interpreter
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    // for natives that block, e.g. on a child process, and need to stop early
+    pub(crate) fn interrupt(&self) -> Interrupt {
+        Interrupt {
+            cancel: self.cancel.clone(),
+            deadline: Shared::clone(&self.deadline),
+        }
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
     }
+
+    // wall-clock limit for each call to interpret, None means no limit
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    // called once for every expression evaluated
+    pub(crate) fn step(&mut self) -> Result<(), RuntimeError<'err>> {
+        self.steps += 1;
+        if self.cancel.take() {
+            return Err(self.halt(Halt::Cancelled, None, "Evaluation was cancelled".into()));
+        }
+        if self.steps.is_multiple_of(TIMEOUT_CHECK_INTERVAL) {
+            self.check_deadline(None)?;
+        }
+        match self.limits.max_steps {
+            Some(max) if self.steps > max => Err(self.halt(
+                Halt::Steps,
+                None,
+                format!("Exceeded the limit of {} evaluation steps", max),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // natives may block, so the clock is always checked before a call
+    pub(crate) fn enter_call(&mut self, paren: &Token<'_>) -> Result<(), RuntimeError<'err>> {
+        self.check_deadline(Some(paren))?;
+        if self.stack_base.abs_diff(stack_address()) > MAX_CALL_STACK {
+            return Err(self.halt(
+                Halt::CallDepth,
+                Some(paren),
+                "Exceeded the stack space for nested calls".into(),
+            ));
+        }
+        match self.limits.max_call_depth {
+            Some(max) if self.call_depth >= max => Err(self.halt(
+                Halt::CallDepth,
+                Some(paren),
+                format!("Exceeded the limit of {} nested calls", max),
+            )),
+            _ => {
+                self.call_depth += 1;
+                Ok(())
+            }
+        }
+    }
+
+    pub(crate) fn exit_call(&mut self) {
+        self.call_depth -= 1;
+    }
+
+    // checked whenever the script creates or grows a string or container
+    pub(crate) fn check_size(
+        &mut self,
+        value: &Value,
+        token: Option<&Token<'_>>,
+    ) -> Result<(), RuntimeError<'err>> {
+        match self.limits.max_value_size {
+            Some(max) if value.size() > max => Err(self.halt(
+                Halt::Memory,
+                token,
+                format!(
+                    "Exceeded the limit of {} for the size of a {}",
+                    max,
+                    value.type_name()
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // a native that gave up because of a cancel or the timeout is reported as that rather than
+    // as its own error
+    pub(crate) fn check_interrupted(
+        &mut self,
+        paren: &Token<'_>,
+    ) -> Result<(), RuntimeError<'err>> {
+        if self.cancel.take() {
+            return Err(self.halt(
+                Halt::Cancelled,
+                Some(paren),
+                "Evaluation was cancelled".into(),
+            ));
+        }
+        self.check_deadline(Some(paren))
+    }
+
+    fn check_deadline(&mut self, token: Option<&Token<'_>>) -> Result<(), RuntimeError<'err>> {
+        let deadline = *self.deadline.borrow();
+        match deadline {
+            Some(deadline) if Instant::now() >= deadline => Err(self.halt(
+                Halt::Timeout,
+                token,
+                format!(
+                    "Exceeded the timeout of {:?}",
+                    self.timeout.unwrap_or_default()
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn halt(&mut self, halt: Halt, token: Option<&Token<'_>>, msg: String) -> RuntimeError<'err> {
+        self.halt = Some(halt);
+        RuntimeError {
+            token: token.map(Token::deep_clone),
+            msg,
+            trace: Vec::new(),
+        }
+    }
+
+    // the error that carries a thrown value out to a catch block, uncaught it is reported with the
+    // value's message: the string itself, or the message of an error object
+    pub(crate) fn throw(&mut self, keyword: &Token<'_>, value: Value) -> RuntimeError<'err> {
+        let msg = match &value {
+            Value::Map(map) => match map.borrow().get(&Value::String("message".into())) {
+                Ok(Some(Value::String(msg))) => Some(msg.clone()),
+                _ => None,
+            },
+            _ => None,
+        };
+        let msg = msg.unwrap_or_else(|| value.to_string());
+        self.thrown = Some(value);
+        RuntimeError {
+            token: Some(keyword.deep_clone()),
+            msg,
+            trace: Vec::new(),
+        }
+    }
+
+    // what a catch block gets for an error: the thrown value, or else an error object with the
+    // message and location of the runtime error; errors that halt the evaluation can't be caught
+    pub(crate) fn catch(&mut self, error: RuntimeError<'err>) -> Result<Value, RuntimeError<'err>> {
+        if self.halt.is_some() {
+            return Err(error);
+        }
+        if let Some(value) = self.thrown.take() {
+            return Ok(value);
+        }
+        let (line, column) = match &error.token {
+            Some(token) => (
+                Value::Number(token.line as f64),
+                Value::Number(token.column as f64),
+            ),
+            None => (Value::Nil, Value::Nil),
+        };
+        let mut object = Map::new();
+        for (key, value) in [
+            ("message", Value::String(error.msg)),
+            ("line", line),
+            ("column", column),
+        ] {
+            object.insert(Value::String(key.into()), value).unwrap();
+        }
+        Ok(Value::Map(Shared::new(Lock::new(object))))
+    }
+
+    // runs a finally block, the value thrown before it is kept unless the block throws its own
+    // a halted evaluation stops at once, without running finally blocks
+    pub(crate) fn finally<E>(
+        &mut self,
+        block: impl FnOnce(&mut Self) -> Result<Value, E>,
+    ) -> Result<(), E> {
+        if self.halt.is_some() {
+            return Ok(());
+        }
+        let thrown = self.thrown.take();
+        block(self)?;
+        self.thrown = thrown;
+        Ok(())
+    }
+
+    pub fn global(&self, name: &str) -> Option<&Value> {
+        self.globals.get(name)
+    }
+
+    // the variable in the innermost scope that has it, or else the global
+    pub(crate) fn lookup(&self, name: &str) -> Option<Value> {
+        let mut scope = self.scope.clone();
+        while let Some(current) = scope {
+            let current = current.borrow();
+            if let Some(value) = current.variables.get(name) {
+                return Some(value.clone());
+            }
+            scope = current.enclosing.clone();
+        }
+        self.globals.get(name).cloned()
+    }
+
+    // updates the variable that lookup would find, a new variable is local to the running call
+    // or global at the top level
+    pub(crate) fn assign(&mut self, name: &str, value: Value) {
+        let mut scope = self.scope.clone();
+        while let Some(current) = scope {
+            let mut current = current.borrow_mut();
+            if let Some(variable) = current.variables.get_mut(name) {
+                *variable = value;
+                return;
+            }
+            scope = current.enclosing.clone();
+        }
+        match &self.scope {
+            Some(_) if !self.globals.contains_key(name) => self.declare(name.to_owned(), value),
+            _ => self.define(name, value),
+        }
+    }
+
+    // a variable of the running call, which hides any outer variable with the same name
+    pub(crate) fn declare(&mut self, name: String, value: Value) {
+        match &self.scope {
+            Some(scope) => {
+                scope.borrow_mut().variables.insert(name, value);
+            }
+            None => {
+                self.globals.insert(name, value);
+            }
+        }
+    }
+
+    // a closure over the current scope, None when no program is running
+    pub(crate) fn closure(&self, id: usize) -> Option<Closure> {
+        Some(Closure {
+            program: self.program.clone()?,
+            id,
+            scope: self.scope.clone(),
+        })
+    }
+
+    // runs the body of a closure in the given scope, then switches back to the caller's
+    pub(crate) fn call_in<T>(
+        &mut self,
+        closure: &Closure,
+        scope: Scope,
+        body: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let scope = Some(Shared::new(Lock::new(scope)));
+        let caller_scope = std::mem::replace(&mut self.scope, scope);
+        let caller_program = self.program.replace(closure.program.clone());
+        let value = body(self);
+        self.scope = caller_scope;
+        self.program = caller_program;
+        value
+    }
+
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.globals.insert(name.to_owned(), value);
+    }
+
+    pub fn streams(&self) -> &Streams {
+        &self.streams
+    }
+
+    // shared with the natives, which check it when they are called
+    pub(crate) fn capabilities(&self) -> Shared<SyncLock<Capabilities>> {
+        Shared::clone(&self.capabilities)
+    }
+
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        *self.capabilities.borrow_mut() = capabilities;
+    }
+}
+
+// where the stack of the current thread is, it grows by the frames of nested calls
+#[inline(never)]
+fn stack_address() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
 }
@@ -0,0 +1,145 @@
+use crate::error::RuntimeError;
+use crate::sync::Shared;
+use crate::value::{HostObject, Value};
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// what a for-in loop walks over, the one path that every iterable value goes through
+// lists, tuples and maps (their keys) are walked over a snapshot, so the loop body may change them
+// host objects follow the iterator protocol: iter() returns an iterator object, whose next()
+// returns one value per call and nil once there are none left
+pub(crate) enum Iteration {
+    Values(std::vec::IntoIter<Value>),
+    // the string and the byte offset of its next character
+    Chars(String, usize),
+    Host(Shared<dyn HostObject>),
+}
+
+impl Iteration {
+    pub(crate) fn new(value: &Value) -> Result<Self, RuntimeError<'static>> {
+        match value {
+            Value::List(list) => Ok(Self::Values(list.borrow().clone().into_iter())),
+            Value::Tuple(tuple) => Ok(Self::Values(Vec::from(&tuple[..]).into_iter())),
+            Value::Map(map) => Ok(Self::Values(
+                map.borrow()
+                    .iter()
+                    .map(|(key, _)| key.clone())
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+            )),
+            Value::String(s) => Ok(Self::Chars(s.clone(), 0)),
+            Value::HostObject(object) => match object.call_method("iter", &[])? {
+                Value::HostObject(iterator) => Ok(Self::Host(iterator)),
+                value => Err(RuntimeError::new(format!(
+                    "iter() on {} must return an iterator object, got {}",
+                    object.class_name(),
+                    value.type_name()
+                ))),
+            },
+            _ => Err(RuntimeError::new(format!(
+                "Only lists, tuples, maps, strings and iterable objects can be iterated, got {}",
+                value.type_name()
+            ))),
+        }
+    }
+
+    pub(crate) fn next(&mut self) -> Result<Option<Value>, RuntimeError<'static>> {
+        match self {
+            Self::Values(values) => Ok(values.next()),
+            Self::Chars(s, offset) => Ok(s[*offset..].chars().next().map(|c| {
+                *offset += c.len_utf8();
+                Value::String(c.into())
+            })),
+            Self::Host(iterator) => match iterator.call_method("next", &[])? {
+                Value::Nil => Ok(None),
+                value => Ok(Some(value)),
+            },
+        }
+    }
+}
+
+// the `range` native's result: the numbers from start up to (not including) end, counting by step
+// values are computed as they are needed, so a range takes no memory however long it is
+pub(crate) struct Range {
+    start: f64,
+    end: f64,
+    step: f64,
+}
+
+impl Range {
+    pub(crate) fn new(start: f64, end: f64, step: f64) -> Self {
+        Self { start, end, step }
+    }
+}
+
+impl HostObject for Range {
+    fn class_name(&self) -> &str {
+        "range"
+    }
+
+    fn get(&self, name: &str) -> Result<Value, RuntimeError<'static>> {
+        match name {
+            "start" => Ok(Value::Number(self.start)),
+            "end" => Ok(Value::Number(self.end)),
+            "step" => Ok(Value::Number(self.step)),
+            _ => Err(RuntimeError::new(format!(
+                "Undefined property '{}' on range",
+                name
+            ))),
+        }
+    }
+
+    fn call_method(&self, name: &str, arguments: &[Value]) -> Result<Value, RuntimeError<'static>> {
+        match (name, arguments) {
+            ("iter", []) => Ok(Value::HostObject(Shared::new(RangeIterator {
+                start: self.start,
+                end: self.end,
+                step: self.step,
+                index: AtomicU64::new(0),
+            }))),
+            ("iter", _) => Err(RuntimeError::new(format!(
+                "range.iter() expects 0 argument(s) but got {}",
+                arguments.len()
+            ))),
+            _ => Err(RuntimeError::new(format!(
+                "Undefined method '{}' on range",
+                name
+            ))),
+        }
+    }
+}
+
+struct RangeIterator {
+    start: f64,
+    end: f64,
+    step: f64,
+    index: AtomicU64,
+}
+
+impl HostObject for RangeIterator {
+    fn class_name(&self) -> &str {
+        "range iterator"
+    }
+
+    // start + index * step rather than a running sum, so that fractional steps don't drift
+    fn call_method(
+        &self,
+        name: &str,
+        _arguments: &[Value],
+    ) -> Result<Value, RuntimeError<'static>> {
+        if name != "next" {
+            return Err(RuntimeError::new(format!(
+                "Undefined method '{}' on range iterator",
+                name
+            )));
+        }
+        let index = self.index.fetch_add(1, Ordering::Relaxed);
+        let n = self.start + index as f64 * self.step;
+        let more = if self.step > 0.0 {
+            n < self.end
+        } else {
+            n > self.end
+        };
+        Ok(if more { Value::Number(n) } else { Value::Nil })
+    }
+}
@@ -0,0 +1,406 @@
+use crate::error::RuntimeError;
+use crate::sync::{Lock, Shared};
+use crate::value::{HostObject, Map, Value};
+
+use std::fmt::Write;
+
+// objects and arrays nested deeper than this are rejected instead of overflowing the stack
+const MAX_NESTING_DEPTH: usize = 256;
+
+// wider indents are treated as this many spaces, like JSON.stringify does
+const MAX_INDENT: usize = 10;
+
+// the `json` global: json.parse(text) and json.stringify(value) or json.stringify(value, indent)
+pub(crate) struct Json;
+
+impl HostObject for Json {
+    fn class_name(&self) -> &str {
+        "json"
+    }
+
+    fn call_method(&self, name: &str, arguments: &[Value]) -> Result<Value, RuntimeError<'static>> {
+        match (name, arguments) {
+            ("parse", [Value::String(text)]) => parse(text),
+            ("parse", [_]) => Err(RuntimeError::new("json.parse() expects a string")),
+            ("stringify", [value]) => stringify(value, None),
+            ("stringify", [value, Value::Nil]) => stringify(value, None),
+            ("stringify", [value, Value::Number(n)]) if n.fract() == 0.0 && *n >= 0.0 => {
+                stringify(value, Some(n.min(MAX_INDENT as f64) as usize))
+            }
+            ("stringify", [_, _]) => Err(RuntimeError::new(
+                "json.stringify() expects the indent to be a non-negative integer or nil",
+            )),
+            ("parse", _) => Err(RuntimeError::new(format!(
+                "json.parse() expects 1 argument(s) but got {}",
+                arguments.len()
+            ))),
+            ("stringify", _) => Err(RuntimeError::new(format!(
+                "json.stringify() expects 1 or 2 argument(s) but got {}",
+                arguments.len()
+            ))),
+            _ => Err(RuntimeError::new(format!(
+                "Undefined method '{}' on json",
+                name
+            ))),
+        }
+    }
+}
+
+// objects become maps (keeping the order of their keys) and arrays become lists
+fn parse(text: &str) -> Result<Value, RuntimeError<'static>> {
+    let mut parser = JsonParser {
+        chars: text.chars().collect(),
+        curr: 0,
+        line: 1,
+        column: 1,
+        depth: 0,
+    };
+    parser.skip_whitespace();
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.peek().is_some() {
+        return Err(parser.error("Unexpected text after the JSON value"));
+    }
+    Ok(value)
+}
+
+struct JsonParser {
+    chars: Vec<char>,
+    curr: usize,
+    line: usize,
+    column: usize,
+    depth: usize,
+}
+
+impl JsonParser {
+    fn value(&mut self) -> Result<Value, RuntimeError<'static>> {
+        match self.peek() {
+            Some('{') => self.nested(Self::object),
+            Some('[') => self.nested(Self::array),
+            Some('"') => Ok(Value::String(self.string()?)),
+            Some('-' | '0'..='9') => self.number(),
+            Some('t') => self.keyword("true", Value::Boolean(true)),
+            Some('f') => self.keyword("false", Value::Boolean(false)),
+            Some('n') => self.keyword("null", Value::Nil),
+            Some(c) => Err(self.error(&format!("Unexpected character '{}'", c))),
+            None => Err(self.error("Unexpected end of JSON text")),
+        }
+    }
+
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Value, RuntimeError<'static>>,
+    ) -> Result<Value, RuntimeError<'static>> {
+        if self.depth >= MAX_NESTING_DEPTH {
+            return Err(self.error("JSON is nested too deeply"));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn object(&mut self) -> Result<Value, RuntimeError<'static>> {
+        self.advance();
+        let mut map = Map::new();
+        self.skip_whitespace();
+        if !self.match_char('}') {
+            loop {
+                self.skip_whitespace();
+                if self.peek() != Some('"') {
+                    return Err(self.error("Expect string key in JSON object"));
+                }
+                let key = self.string()?;
+                self.skip_whitespace();
+                self.consume(':', "Expect ':' after key in JSON object")?;
+                self.skip_whitespace();
+                let value = self.value()?;
+                // a repeated key keeps the last value, like JSON.parse
+                map.insert(Value::String(key), value)
+                    .map_err(|msg| self.error(msg))?;
+                self.skip_whitespace();
+                if self.match_char('}') {
+                    break;
+                }
+                self.consume(',', "Expect ',' or '}' in JSON object")?;
+            }
+        }
+        Ok(Value::Map(Shared::new(Lock::new(map))))
+    }
+
+    fn array(&mut self) -> Result<Value, RuntimeError<'static>> {
+        self.advance();
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if !self.match_char(']') {
+            loop {
+                self.skip_whitespace();
+                values.push(self.value()?);
+                self.skip_whitespace();
+                if self.match_char(']') {
+                    break;
+                }
+                self.consume(',', "Expect ',' or ']' in JSON array")?;
+            }
+        }
+        Ok(Value::List(Shared::new(Lock::new(values))))
+    }
+
+    fn string(&mut self) -> Result<String, RuntimeError<'static>> {
+        self.advance();
+        let mut string = String::new();
+        loop {
+            match self.advance() {
+                Some('"') => return Ok(string),
+                Some('\\') => string.push(self.escape()?),
+                Some(c) if c < ' ' => {
+                    return Err(self.error("Control characters must be escaped in JSON strings"))
+                }
+                Some(c) => string.push(c),
+                None => return Err(self.error("Unterminated JSON string")),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, RuntimeError<'static>> {
+        match self.advance() {
+            Some('"') => Ok('"'),
+            Some('\\') => Ok('\\'),
+            Some('/') => Ok('/'),
+            Some('b') => Ok('\u{8}'),
+            Some('f') => Ok('\u{c}'),
+            Some('n') => Ok('\n'),
+            Some('r') => Ok('\r'),
+            Some('t') => Ok('\t'),
+            Some('u') => {
+                let unit = self.hex4()?;
+                // characters outside the basic plane are written as a surrogate pair
+                let code = if (0xD800..0xDC00).contains(&unit) {
+                    if !(self.match_char('\\') && self.match_char('u')) {
+                        return Err(self.error("Expect low surrogate after high surrogate"));
+                    }
+                    let low = self.hex4()?;
+                    if !(0xDC00..0xE000).contains(&low) {
+                        return Err(self.error("Invalid low surrogate"));
+                    }
+                    0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00)
+                } else {
+                    unit
+                };
+                char::from_u32(code).ok_or_else(|| self.error("Invalid unicode escape"))
+            }
+            _ => Err(self.error("Invalid escape sequence in JSON string")),
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, RuntimeError<'static>> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self
+                .advance()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| self.error("Expect 4 hex digits after '\\u'"))?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    // -? (0 | [1-9][0-9]*) (. [0-9]+)? ([eE] [+-]? [0-9]+)?
+    fn number(&mut self) -> Result<Value, RuntimeError<'static>> {
+        let start = self.curr;
+        self.match_char('-');
+        if !self.match_char('0') && !self.digits() {
+            return Err(self.error("Expect digit in JSON number"));
+        }
+        if self.match_char('.') && !self.digits() {
+            return Err(self.error("Expect digit after '.' in JSON number"));
+        }
+        if self.match_char('e') || self.match_char('E') {
+            if !self.match_char('+') {
+                self.match_char('-');
+            }
+            if !self.digits() {
+                return Err(self.error("Expect digit in JSON number exponent"));
+            }
+        }
+        let text: String = self.chars[start..self.curr].iter().collect();
+        Ok(Value::Number(text.parse().unwrap()))
+    }
+
+    // true if at least one digit was consumed
+    fn digits(&mut self) -> bool {
+        let start = self.curr;
+        while matches!(self.peek(), Some('0'..='9')) {
+            self.advance();
+        }
+        self.curr > start
+    }
+
+    fn keyword(&mut self, word: &str, value: Value) -> Result<Value, RuntimeError<'static>> {
+        for expected in word.chars() {
+            if !self.match_char(expected) {
+                return Err(self.error(&format!("Invalid literal, expected '{}'", word)));
+            }
+        }
+        Ok(value)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.advance();
+        }
+    }
+
+    fn consume(&mut self, expected: char, msg: &str) -> Result<(), RuntimeError<'static>> {
+        if self.match_char(expected) {
+            Ok(())
+        } else {
+            Err(self.error(msg))
+        }
+    }
+
+    fn match_char(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.curr += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.curr).copied()
+    }
+
+    // reported at the current position in the JSON text, not in the script
+    fn error(&self, msg: &str) -> RuntimeError<'static> {
+        RuntimeError::new(format!(
+            "Invalid JSON at line {}, column {}: {}",
+            self.line, self.column, msg
+        ))
+    }
+}
+
+// lists and tuples become arrays and maps with string keys become objects
+// with an indent, nested values go on their own lines like JSON.stringify
+fn stringify(value: &Value, indent: Option<usize>) -> Result<Value, RuntimeError<'static>> {
+    let mut json = String::new();
+    write_value(&mut json, value, indent.filter(|&n| n > 0), 0)?;
+    Ok(Value::String(json))
+}
+
+fn write_value(
+    json: &mut String,
+    value: &Value,
+    indent: Option<usize>,
+    depth: usize,
+) -> Result<(), RuntimeError<'static>> {
+    if depth >= MAX_NESTING_DEPTH {
+        return Err(RuntimeError::new(
+            "Value is nested too deeply to convert to JSON",
+        ));
+    }
+    match value {
+        Value::Number(n) if !n.is_finite() => {
+            return Err(RuntimeError::new(
+                "NaN and infinity cannot be converted to JSON",
+            ))
+        }
+        Value::Number(n) => write!(json, "{}", n).unwrap(),
+        Value::String(s) => write_string(json, s),
+        Value::Boolean(b) => write!(json, "{}", b).unwrap(),
+        Value::Nil => json.push_str("null"),
+        Value::List(list) => write_array(json, &list.borrow(), indent, depth)?,
+        Value::Tuple(tuple) => write_array(json, tuple, indent, depth)?,
+        Value::Map(map) => {
+            let map = map.borrow();
+            if map.is_empty() {
+                json.push_str("{}");
+                return Ok(());
+            }
+            json.push('{');
+            for (i, (key, value)) in map.iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                let Value::String(key) = key else {
+                    return Err(RuntimeError::new(format!(
+                        "JSON object keys must be strings but got {}",
+                        key.type_name()
+                    )));
+                };
+                new_line(json, indent, depth + 1);
+                write_string(json, key);
+                json.push_str(if indent.is_some() { ": " } else { ":" });
+                write_value(json, value, indent, depth + 1)?;
+            }
+            new_line(json, indent, depth);
+            json.push('}');
+        }
+        Value::NativeFunction(_) | Value::Function(_) | Value::HostObject(_) => {
+            return Err(RuntimeError::new(format!(
+                "A {} cannot be converted to JSON",
+                value.type_name()
+            )))
+        }
+    }
+    Ok(())
+}
+
+fn write_array(
+    json: &mut String,
+    values: &[Value],
+    indent: Option<usize>,
+    depth: usize,
+) -> Result<(), RuntimeError<'static>> {
+    if values.is_empty() {
+        json.push_str("[]");
+        return Ok(());
+    }
+    json.push('[');
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        new_line(json, indent, depth + 1);
+        write_value(json, value, indent, depth + 1)?;
+    }
+    new_line(json, indent, depth);
+    json.push(']');
+    Ok(())
+}
+
+fn new_line(json: &mut String, indent: Option<usize>, depth: usize) {
+    if let Some(indent) = indent {
+        json.push('\n');
+        json.push_str(&" ".repeat(indent * depth));
+    }
+}
+
+fn write_string(json: &mut String, s: &str) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c < ' ' => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
@@ -1,58 +1,105 @@
+// values are shared with Arc even when their Lock is a RefCell, so that the public types are the
+// same with and without the `sync` feature
+#![cfg_attr(not(feature = "sync"), allow(clippy::arc_with_non_send_sync))]
+
+mod capabilities;
+mod convert;
+mod engine;
 mod error;
 mod expr;
 mod interpreter;
+mod iteration;
+mod json;
+mod natives;
 mod parser;
+mod program;
 mod scanner;
+#[cfg(feature = "serde")]
+mod serialization;
+mod streams;
+mod sync;
 mod token;
 mod value;
 
+pub use capabilities::Capabilities;
+pub use convert::{FromLox, IntoLox};
+pub use engine::Lox;
+pub use error::{CallFrame, LexError, LoxError, ParseError, RuntimeError};
+pub use expr::Expr;
+pub use interpreter::{CancelHandle, Interpreter, Limits};
+pub use parser::Parser;
+pub use program::Program;
+pub use scanner::Scanner;
+pub use streams::Streams;
+pub use sync::{Lock, Shared};
+pub use token::{Token, TokenType};
+pub use value::{Closure, HostObject, Map, NativeFunction, Value};
+
 use error::Result;
-use interpreter::Interpreter;
-use parser::Parser;
-use scanner::Scanner;
 
-use std::io::{self, Write};
+use std::io::Write;
 
+// the command line runs the user's own scripts, so they get every capability
 pub fn run_prompt() -> Result {
+    run_prompt_with(&mut unrestricted())
+}
+
+pub fn run_file(path: &str) -> Result {
+    run_file_with(&mut unrestricted(), path)
+}
+
+fn unrestricted() -> Lox {
+    let mut lox = Lox::new();
+    lox.set_capabilities(Capabilities::all());
+    lox
+}
+
+// REPL on the session's streams: prompts and results go to its output, errors to its diagnostics
+pub fn run_prompt_with(lox: &mut Lox) -> Result {
+    let streams = lox.streams().clone();
     let mut buffer = String::new();
-    let stdin = std::io::stdin();
 
     loop {
-        print!("> ");
-        io::stdout().flush()?;
-        if let Ok(_) = stdin.read_line(&mut buffer) {
-            run(&buffer)?;
-            buffer.clear();
-        } else {
+        write!(streams.output(), "> ")?;
+        streams.output().flush()?;
+        buffer.clear();
+        if streams.input().read_line(&mut buffer)? == 0 {
             break;
         }
+        if let Err(e) = run(lox, &buffer) {
+            writeln!(streams.diagnostics(), "{}", e)?;
+        }
     }
 
     Ok(())
 }
 
-pub fn run_file(path: &str) -> Result {
+pub fn run_file_with(lox: &mut Lox, path: &str) -> Result {
     let source = std::fs::read_to_string(path)?;
-    run(&source)?;
+    run(lox, &source)?;
 
     Ok(())
 }
 
-fn run(source: &str) -> Result {
-    let scanner = Scanner::new(&source);
+fn run(lox: &mut Lox, source: &str) -> Result {
+    let streams = lox.streams().clone();
+    let scanner = Scanner::new(source);
     let tokens = scanner.scan_tokens()?;
-    print!("\nTokens: ");
+    write!(streams.output(), "\nTokens: ")?;
     for token in &tokens {
-        print!("{} ", token.token_type);
+        write!(streams.output(), "{} ", token.token_type)?;
     }
-    println!("\n");
+    writeln!(streams.output(), "\n")?;
 
-    let parser = Parser::new(&tokens);
-    let expr = parser.parse()?;
-    println!("Expression in prefix notation: {}\n", expr.pretty_print());
+    let program = Program::compile(source)?;
+    writeln!(
+        streams.output(),
+        "Expression in prefix notation: {}\n",
+        program.pretty_print()
+    )?;
 
-    let value = Interpreter::interpret(expr)?;
-    println!("Value: {}", value);
+    let value = lox.run(&program)?;
+    writeln!(streams.output(), "Value: {}", value)?;
 
     Ok(())
 }
@@ -0,0 +1,212 @@
+use crate::capabilities::Capabilities;
+use crate::error::RuntimeError;
+use crate::interpreter::{Interpreter, Interrupt};
+use crate::iteration::Range;
+use crate::json::Json;
+use crate::streams::Streams;
+use crate::sync::Shared;
+use crate::value::Value;
+
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// how often a running child process is checked for having exited or being interrupted
+const EXEC_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// native that checks the session's capabilities before doing any I/O
+type SandboxedNative = fn(&Capabilities, &[Value]) -> Result<Value, RuntimeError<'static>>;
+
+pub(crate) fn define_standard_natives(interpreter: &mut Interpreter) {
+    let streams = interpreter.streams().clone();
+    let capabilities = interpreter.capabilities();
+    interpreter.define_native("input", 0, move |args| input(&streams, args));
+    interpreter.define_native("len", 1, len);
+    interpreter.define_native("range", 3, range);
+    interpreter.define("json", Value::HostObject(Shared::new(Json)));
+
+    let natives: [(&str, usize, SandboxedNative); 4] = [
+        ("clock", 0, clock),
+        ("readFile", 1, read_file),
+        ("writeFile", 2, write_file),
+        ("env", 1, env),
+    ];
+    for (name, arity, function) in natives {
+        let capabilities = Shared::clone(&capabilities);
+        interpreter.define_native(name, arity, move |args| {
+            function(&capabilities.borrow(), args)
+        });
+    }
+
+    let interrupt = interpreter.interrupt();
+    interpreter.define_native("exec", 2, move |args| {
+        exec(&capabilities.borrow(), &interrupt, args)
+    });
+}
+
+// seconds since the unix epoch
+fn clock(capabilities: &Capabilities, _args: &[Value]) -> Result<Value, RuntimeError<'static>> {
+    capabilities.check_clock()?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| RuntimeError::new("System clock is set before the unix epoch"))?;
+    Ok(Value::Number(now.as_secs_f64()))
+}
+
+// next line of the session's input stream without its line ending, nil at end of input
+fn input(streams: &Streams, _args: &[Value]) -> Result<Value, RuntimeError<'static>> {
+    let mut line = String::new();
+    match streams.input().read_line(&mut line) {
+        Ok(0) => Ok(Value::Nil),
+        Ok(_) => {
+            let trimmed = line.trim_end_matches(['\n', '\r']).len();
+            line.truncate(trimmed);
+            Ok(Value::String(line))
+        }
+        Err(e) => Err(RuntimeError::new(format!("Failed to read input: {}", e))),
+    }
+}
+
+fn len(args: &[Value]) -> Result<Value, RuntimeError<'static>> {
+    let len = match &args[0] {
+        Value::String(s) => s.chars().count(),
+        Value::List(list) => list.borrow().len(),
+        Value::Tuple(tuple) => tuple.len(),
+        Value::Map(map) => map.borrow().len(),
+        _ => {
+            return Err(RuntimeError::new(
+                "len() expects a string, list, tuple or map",
+            ))
+        }
+    };
+    Ok(Value::Number(len as f64))
+}
+
+// numbers for a for-in loop, e.g. range(10, 0, -2) gives 10, 8, 6, 4 and 2
+fn range(args: &[Value]) -> Result<Value, RuntimeError<'static>> {
+    let [Value::Number(start), Value::Number(end), Value::Number(step)] = args else {
+        return Err(RuntimeError::new("range() expects three numbers"));
+    };
+    if *step == 0.0 || step.is_nan() {
+        return Err(RuntimeError::new("range() step must not be zero"));
+    }
+    Ok(Value::HostObject(Shared::new(Range::new(
+        *start, *end, *step,
+    ))))
+}
+
+fn string_arg<'a>(
+    name: &str,
+    args: &'a [Value],
+    i: usize,
+) -> Result<&'a str, RuntimeError<'static>> {
+    match &args[i] {
+        Value::String(s) => Ok(s),
+        value => Err(RuntimeError::new(format!(
+            "{}() expects a string as argument {} but got {}",
+            name,
+            i + 1,
+            value.type_name()
+        ))),
+    }
+}
+
+// whole contents of a text file
+fn read_file(capabilities: &Capabilities, args: &[Value]) -> Result<Value, RuntimeError<'static>> {
+    let path = string_arg("readFile", args, 0)?;
+    let resolved = capabilities.check_read(Path::new(path))?;
+    std::fs::read_to_string(resolved)
+        .map(Value::String)
+        .map_err(|e| RuntimeError::new(format!("Failed to read '{}': {}", path, e)))
+}
+
+// creates or replaces the file
+fn write_file(capabilities: &Capabilities, args: &[Value]) -> Result<Value, RuntimeError<'static>> {
+    let path = string_arg("writeFile", args, 0)?;
+    let contents = string_arg("writeFile", args, 1)?;
+    let resolved = capabilities.check_write(Path::new(path))?;
+    std::fs::write(resolved, contents)
+        .map(|_| Value::Nil)
+        .map_err(|e| RuntimeError::new(format!("Failed to write '{}': {}", path, e)))
+}
+
+// value of an environment variable, nil when it isn't set
+fn env(capabilities: &Capabilities, args: &[Value]) -> Result<Value, RuntimeError<'static>> {
+    let name = string_arg("env", args, 0)?;
+    capabilities.check_env(name)?;
+    Ok(std::env::var(name).map_or(Value::Nil, Value::String))
+}
+
+// runs a program (without a shell) with a list of string arguments and returns what it printed
+// the program is killed when the evaluation is cancelled or times out
+fn exec(
+    capabilities: &Capabilities,
+    interrupt: &Interrupt,
+    args: &[Value],
+) -> Result<Value, RuntimeError<'static>> {
+    let program = string_arg("exec", args, 0)?;
+    capabilities.check_process()?;
+    let arguments = match &args[1] {
+        Value::List(list) => list.borrow().clone(),
+        Value::Tuple(tuple) => tuple.to_vec(),
+        _ => return Err(RuntimeError::new("exec() expects a list of arguments")),
+    };
+    let arguments = arguments
+        .iter()
+        .map(|argument| match argument {
+            Value::String(s) => Ok(s.as_str()),
+            _ => Err(RuntimeError::new("exec() arguments must be strings")),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let failed =
+        |e: std::io::Error| RuntimeError::new(format!("Failed to run '{}': {}", program, e));
+    let mut child = Command::new(program)
+        .args(arguments)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(failed)?;
+    // the pipes are drained while waiting so that a chatty program doesn't block on a full pipe
+    let stdout = child.stdout.take().map(read_to_end);
+    let stderr = child.stderr.take().map(read_to_end);
+
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(failed)? {
+            break status;
+        }
+        if interrupt.is_interrupted() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(RuntimeError::new(format!("'{}' was interrupted", program)));
+        }
+        thread::sleep(EXEC_POLL_INTERVAL);
+    };
+    let stdout = stdout
+        .and_then(|reader| reader.join().ok())
+        .unwrap_or_default();
+    let stderr = stderr
+        .and_then(|reader| reader.join().ok())
+        .unwrap_or_default();
+
+    if !status.success() {
+        return Err(RuntimeError::new(format!(
+            "'{}' failed with {}: {}",
+            program,
+            status,
+            String::from_utf8_lossy(&stderr).trim_end()
+        )));
+    }
+    Ok(Value::String(String::from_utf8_lossy(&stdout).into_owned()))
+}
+
+fn read_to_end(mut pipe: impl Read + Send + 'static) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = pipe.read_to_end(&mut bytes);
+        bytes
+    })
+}
@@ -1,12 +1,128 @@
 use crate::error::{LoxError, ParseError};
-use crate::expr::Expr;
+use crate::expr::{Expr, Function, MatchArm, Param, Pattern, Target};
 use crate::token::{Token, TokenType};
 use crate::value::Value;
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
+
+// measured in a debug build, a level of nesting takes up to about 7KB of stack to parse (a match
+// arm's body), evaluate, print or drop, so the deepest input stays below 1MiB, half of the stack
+// of a thread started with std::thread::spawn
+const MAX_NESTING_DEPTH: usize = 128;
+
+// binding power of operators, from loosest to tightest
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+enum Precedence {
+    None,
+    Comma,
+    Assignment,
+    Ternary,
+    Equality,
+    Comparison,
+    Term,
+    Factor,
+    Unary,
+    Postfix,
+    Primary,
+}
+
+impl Precedence {
+    fn next(self) -> Self {
+        match self {
+            Precedence::None => Precedence::Comma,
+            Precedence::Comma => Precedence::Assignment,
+            Precedence::Assignment => Precedence::Ternary,
+            Precedence::Ternary => Precedence::Equality,
+            Precedence::Equality => Precedence::Comparison,
+            Precedence::Comparison => Precedence::Term,
+            Precedence::Term => Precedence::Factor,
+            Precedence::Factor => Precedence::Unary,
+            Precedence::Unary => Precedence::Postfix,
+            Precedence::Postfix | Precedence::Primary => Precedence::Primary,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Associativity {
+    Left,
+    Right,
+}
+
+type PrefixHandler<'token, 'lexeme, 'err> =
+    fn(&Parser<'token, 'lexeme, 'err>) -> Result<Expr<'token, 'lexeme>, ParseError<'err>>;
+type InfixHandler<'token, 'lexeme, 'err> = fn(
+    &Parser<'token, 'lexeme, 'err>,
+    Expr<'token, 'lexeme>,
+) -> Result<Expr<'token, 'lexeme>, ParseError<'err>>;
+
+// the parameters of a function and its rest parameter
+type Params<'token, 'lexeme> = (Vec<Param<'token, 'lexeme>>, Option<&'token Token<'lexeme>>);
+
+// how a token is parsed at the start of an expression (prefix) and after an operand (infix)
+struct ParseRule<'token, 'lexeme, 'err> {
+    prefix: Option<PrefixHandler<'token, 'lexeme, 'err>>,
+    infix: Option<InfixHandler<'token, 'lexeme, 'err>>,
+    precedence: Precedence,
+    associativity: Associativity,
+}
+
+impl<'token, 'lexeme, 'err> ParseRule<'token, 'lexeme, 'err> {
+    fn new(
+        prefix: PrefixHandler<'token, 'lexeme, 'err>,
+        infix: InfixHandler<'token, 'lexeme, 'err>,
+        precedence: Precedence,
+        associativity: Associativity,
+    ) -> Self {
+        Self {
+            prefix: Some(prefix),
+            infix: Some(infix),
+            precedence,
+            associativity,
+        }
+    }
+
+    fn prefix(prefix: PrefixHandler<'token, 'lexeme, 'err>) -> Self {
+        Self {
+            prefix: Some(prefix),
+            infix: None,
+            precedence: Precedence::None,
+            associativity: Associativity::Left,
+        }
+    }
+
+    fn infix(
+        infix: InfixHandler<'token, 'lexeme, 'err>,
+        precedence: Precedence,
+        associativity: Associativity,
+    ) -> Self {
+        Self {
+            prefix: None,
+            infix: Some(infix),
+            precedence,
+            associativity,
+        }
+    }
+
+    fn none() -> Self {
+        Self {
+            prefix: None,
+            infix: None,
+            precedence: Precedence::None,
+            associativity: Associativity::Left,
+        }
+    }
+}
 
 pub struct Parser<'token, 'lexeme, 'err: 'token + 'lexeme> {
     tokens: &'token Vec<Token<'lexeme>>,
     curr: Cell<usize>,
+    depth: Cell<usize>,
+    // labels of the loops around the expression being parsed, innermost last
+    loops: RefCell<Vec<Option<&'token Token<'lexeme>>>>,
+    // number of function expressions parsed so far, the id of the next one
+    functions: Cell<usize>,
+    // index of the '=>' that ends the match guard being parsed, which doesn't make an arrow function
+    guard_end: Cell<Option<usize>>,
     errors: Vec<ParseError<'err>>,
 }
 
@@ -15,6 +131,10 @@ impl<'token, 'lexeme, 'err> Parser<'token, 'lexeme, 'err> {
         Self {
             tokens,
             curr: Cell::new(0),
+            depth: Cell::new(0),
+            loops: RefCell::new(Vec::new()),
+            functions: Cell::new(0),
+            guard_end: Cell::new(None),
             errors: Vec::new(),
         }
     }
@@ -32,94 +152,947 @@ impl<'token, 'lexeme, 'err> Parser<'token, 'lexeme, 'err> {
     }
 
     fn expression(&self) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
-        self.equality()
+        self.parse_precedence(Precedence::Comma)
     }
 
-    // TODO: Create handler function for left-associative rules to simplify redundant code (while loops are nearly identical except token types and variable 'right')
-    fn equality(&self) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
-        let mut expr = self.comparison()?;
+    // operator table: adding an operator means adding its token type here and (if needed) a handler
+    fn rule(token_type: TokenType) -> ParseRule<'token, 'lexeme, 'err> {
+        use Associativity::{Left, Right};
+        match token_type {
+            TokenType::LeftParen => {
+                ParseRule::new(Self::parenthesized, Self::call, Precedence::Postfix, Left)
+            }
+            TokenType::Fun => ParseRule::prefix(Self::function),
+            TokenType::Identifier => ParseRule::prefix(Self::variable),
+            TokenType::LeftBrace => ParseRule::prefix(Self::map),
+            TokenType::Match => ParseRule::prefix(Self::match_expression),
+            TokenType::For => ParseRule::prefix(Self::for_loop),
+            TokenType::Break | TokenType::Continue => ParseRule::prefix(Self::jump),
+            TokenType::Throw => ParseRule::prefix(Self::throw),
+            TokenType::Try => ParseRule::prefix(Self::try_expression),
+            TokenType::False
+            | TokenType::True
+            | TokenType::Nil
+            | TokenType::Number
+            | TokenType::String => ParseRule::prefix(Self::literal),
+            TokenType::InterpolationStart => ParseRule::prefix(Self::interpolation),
+            TokenType::Bang => ParseRule::prefix(Self::unary),
+            TokenType::Comma => ParseRule::infix(Self::comma, Precedence::Comma, Left),
+            TokenType::Equal
+            | TokenType::PlusEqual
+            | TokenType::MinusEqual
+            | TokenType::StarEqual
+            | TokenType::SlashEqual => {
+                ParseRule::infix(Self::assignment, Precedence::Assignment, Right)
+            }
+            TokenType::Question => ParseRule::infix(Self::ternary, Precedence::Ternary, Right),
+            TokenType::BangEqual | TokenType::EqualEqual => ParseRule::new(
+                Self::missing_left_operand,
+                Self::binary,
+                Precedence::Equality,
+                Left,
+            ),
+            TokenType::Greater
+            | TokenType::GreaterEqual
+            | TokenType::Less
+            | TokenType::LessEqual => ParseRule::new(
+                Self::missing_left_operand,
+                Self::binary,
+                Precedence::Comparison,
+                Left,
+            ),
+            TokenType::Minus => ParseRule::new(Self::unary, Self::binary, Precedence::Term, Left),
+            TokenType::Plus => ParseRule::new(
+                Self::missing_left_operand,
+                Self::binary,
+                Precedence::Term,
+                Left,
+            ),
+            TokenType::Slash | TokenType::Star => ParseRule::new(
+                Self::missing_left_operand,
+                Self::binary,
+                Precedence::Factor,
+                Left,
+            ),
+            TokenType::LeftBracket => {
+                ParseRule::new(Self::list, Self::index, Precedence::Postfix, Left)
+            }
+            TokenType::Dot => ParseRule::infix(Self::property, Precedence::Postfix, Left),
+            TokenType::PlusPlus | TokenType::MinusMinus => ParseRule::new(
+                Self::prefix_increment,
+                Self::postfix_increment,
+                Precedence::Postfix,
+                Left,
+            ),
+            _ => ParseRule::none(),
+        }
+    }
+
+    // nesting is bounded so that deeply nested input is reported instead of overflowing the stack
+    // when the tree is parsed, evaluated, printed or dropped
+    fn parse_precedence(
+        &self,
+        precedence: Precedence,
+    ) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let depth = self.depth.get();
+        let expr = self.parse_operators(precedence);
+        self.depth.set(depth);
+        expr
+    }
 
-        while self.match_token_types(&vec![TokenType::BangEqual, TokenType::EqualEqual]) {
-            let operator = self.prev();
-            let right = self.comparison()?;
-            expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
+    fn enter_nesting(&self) -> Result<(), ParseError<'err>> {
+        if self.depth.get() >= MAX_NESTING_DEPTH {
+            return Err(ParseError {
+                token: self.peek().deep_clone(),
+                msg: "Expression is nested too deeply".into(),
+            });
+        }
+        self.depth.set(self.depth.get() + 1);
+        Ok(())
+    }
+
+    fn parse_operators(
+        &self,
+        precedence: Precedence,
+    ) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        self.enter_nesting()?;
+        let Some(prefix) = Self::rule(self.peek().token_type).prefix else {
+            return Err(ParseError {
+                token: self.peek().deep_clone(),
+                msg: "Expect expression".into(),
+            });
+        };
+        self.advance();
+        let mut expr = prefix(self)?;
+
+        loop {
+            let rule = Self::rule(self.peek().token_type);
+            match rule.infix {
+                Some(infix) if rule.precedence >= precedence => {
+                    // an infix operator wraps everything parsed so far unless it extends a chain
+                    if !Self::extends_chain(&expr, self.peek().token_type) {
+                        self.enter_nesting()?;
+                    }
+                    self.advance();
+                    expr = infix(self, expr)?;
+                }
+                _ => break,
+            }
         }
 
         Ok(expr)
     }
 
-    fn comparison(&self) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
-        let mut expr = self.term()?;
+    // binary operators and commas append to a chain on their left instead of nesting it
+    fn extends_chain(left: &Expr<'token, 'lexeme>, token_type: TokenType) -> bool {
+        match left {
+            Expr::Comma(_) => token_type == TokenType::Comma,
+            Expr::Binary(..) => matches!(
+                token_type,
+                TokenType::BangEqual
+                    | TokenType::EqualEqual
+                    | TokenType::Greater
+                    | TokenType::GreaterEqual
+                    | TokenType::Less
+                    | TokenType::LessEqual
+                    | TokenType::Minus
+                    | TokenType::Plus
+                    | TokenType::Slash
+                    | TokenType::Star
+            ),
+            _ => false,
+        }
+    }
 
-        while self.match_token_types(&vec![
-            TokenType::Greater,
-            TokenType::GreaterEqual,
-            TokenType::Less,
-            TokenType::LessEqual,
-        ]) {
-            let operator = self.prev();
-            let right = self.term()?;
-            expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
+    // precedence of the right operand of the previous (infix) operator
+    fn right_operand_precedence(&self) -> Precedence {
+        let rule = Self::rule(self.prev().token_type);
+        match rule.associativity {
+            Associativity::Left => rule.precedence.next(),
+            Associativity::Right => rule.precedence,
         }
+    }
 
-        Ok(expr)
+    fn literal(&self) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        Ok(Expr::Literal(Self::literal_value(self.prev())))
     }
 
-    fn term(&self) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
-        let mut expr = self.factor()?;
+    // a name followed by ':' and 'for' labels the loop, e.g. outer: for (x in xs) ...
+    fn variable(&self) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let name = self.prev();
+        if self.check(TokenType::Colon) && self.check_next(TokenType::For) {
+            self.advance();
+            self.advance();
+            return self.labelled_loop(Some(name));
+        }
+        Ok(Expr::Variable(name))
+    }
 
-        while self.match_token_types(&vec![TokenType::Minus, TokenType::Plus]) {
-            let operator = self.prev();
-            let right = self.factor()?;
-            expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
+    fn literal_value(token: &Token<'lexeme>) -> Value {
+        match token.token_type {
+            TokenType::False => Value::Boolean(false),
+            TokenType::True => Value::Boolean(true),
+            TokenType::Nil => Value::Nil,
+            _ => token.literal.as_ref().unwrap().into(),
         }
+    }
 
-        Ok(expr)
+    // (a, b) => body is an arrow function, anything else in parentheses a grouping or tuple
+    fn parenthesized(&self) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        if self.is_arrow_function() {
+            let paren = self.prev();
+            let (params, rest) = self.params()?;
+            self.consume(TokenType::FatArrow, "Expect '=>' after parameters")?;
+            return self.function_body(paren, params, rest, Precedence::Assignment);
+        }
+        self.grouping()
     }
 
-    fn factor(&self) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
-        let mut expr = self.unary()?;
+    // whether the '(' just consumed is closed by a ')' followed by '=>', other than the one ending
+    // a match guard, e.g. the guard in: _ if (ready) => 1
+    fn is_arrow_function(&self) -> bool {
+        let mut depth = 1;
+        for (i, token) in self.tokens.iter().enumerate().skip(self.curr.get()) {
+            match token.token_type {
+                TokenType::LeftParen => depth += 1,
+                TokenType::RightParen if depth == 1 => {
+                    return self.tokens[i + 1].token_type == TokenType::FatArrow
+                        && self.guard_end.get() != Some(i + 1);
+                }
+                TokenType::RightParen => depth -= 1,
+                TokenType::EOF => return false,
+                _ => (),
+            }
+        }
+        false
+    }
 
-        while self.match_token_types(&vec![TokenType::Slash, TokenType::Star]) {
-            let operator = self.prev();
-            let right = self.unary()?;
-            expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
+    // fun (a, b) { body }, a function is a value and is assigned to give it a name
+    fn function(&self) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let keyword = self.prev();
+        if self.check(TokenType::Identifier) && self.check_next(TokenType::LeftParen) {
+            return Err(ParseError {
+                token: self.peek().deep_clone(),
+                msg: format!(
+                    "Functions have no names, assign one instead: {} = fun (...) {{ ... }}",
+                    String::from_utf8_lossy(&self.peek().lexeme)
+                ),
+            });
         }
+        self.consume(TokenType::LeftParen, "Expect '(' after 'fun'")?;
+        let (params, rest) = self.params()?;
+        self.consume(TokenType::LeftBrace, "Expect '{' before function body")?;
+        if self.match_token_types(&vec![TokenType::RightBrace]) {
+            return self.function_body(keyword, params, rest, Precedence::None);
+        }
+        let function = self.function_body(keyword, params, rest, Precedence::Comma)?;
+        self.consume(TokenType::RightBrace, "Expect '}' after function body")?;
+        Ok(function)
+    }
 
-        Ok(expr)
+    // parameters up to and including the ')': names, each with an optional = default, and
+    // optionally a ...rest parameter last
+    fn params(&self) -> Result<Params<'token, 'lexeme>, ParseError<'err>> {
+        let mut params: Vec<Param> = Vec::new();
+        let mut rest = None;
+        while !self.check(TokenType::RightParen) {
+            let is_rest = self.match_token_types(&vec![TokenType::DotDotDot]);
+            let name = self.consume(TokenType::Identifier, "Expect parameter name")?;
+            if params.iter().any(|param| param.name.lexeme == name.lexeme) {
+                return Err(ParseError {
+                    token: name.deep_clone(),
+                    msg: "Duplicate parameter name".into(),
+                });
+            }
+            if is_rest {
+                rest = Some(name);
+                self.consume(TokenType::RightParen, "Expect ')' after rest parameter")?;
+                return Ok((params, rest));
+            }
+            let default = if self.match_token_types(&vec![TokenType::Equal]) {
+                Some(self.outside_loops(|| self.parse_precedence(Precedence::Assignment))?)
+            } else if params.last().is_some_and(|param| param.default.is_some()) {
+                return Err(ParseError {
+                    token: name.deep_clone(),
+                    msg: "Expect default value after parameters with one".into(),
+                });
+            } else {
+                None
+            };
+            params.push(Param { name, default });
+            if !self.match_token_types(&vec![TokenType::Comma]) {
+                break;
+            }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after parameters")?;
+        Ok((params, rest))
+    }
+
+    // loops around a function don't continue into it, a break there would leave the call
+    fn outside_loops<T>(&self, parse: impl FnOnce() -> T) -> T {
+        let loops = self.loops.take();
+        let parsed = parse();
+        self.loops.replace(loops);
+        parsed
+    }
+
+    // the body is parsed with the given precedence, or is nil for Precedence::None (an empty body)
+    fn function_body(
+        &self,
+        token: &'token Token<'lexeme>,
+        params: Vec<Param<'token, 'lexeme>>,
+        rest: Option<&'token Token<'lexeme>>,
+        precedence: Precedence,
+    ) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let id = self.functions.get();
+        self.functions.set(id + 1);
+        let body = if precedence == Precedence::None {
+            Expr::Literal(Value::Nil)
+        } else {
+            self.outside_loops(|| self.parse_precedence(precedence))?
+        };
+        Ok(Expr::Function(Box::new(Function {
+            token,
+            params,
+            rest,
+            body,
+            id,
+        })))
+    }
+
+    // a comma inside parentheses makes a tuple: (), (a,) and (a, b) are tuples, (a) is a grouping
+    fn grouping(&self) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let paren = self.prev();
+        if self.match_token_types(&vec![TokenType::RightParen]) {
+            return Ok(Expr::Tuple(Vec::new(), paren));
+        }
+
+        let expr = self.parse_precedence(Precedence::Assignment)?;
+        if self.match_token_types(&vec![TokenType::Comma]) {
+            return self.tuple(expr, paren);
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after expression")?;
+        Ok(Expr::Grouping(Box::new(expr)))
+    }
+
+    // the rest of a tuple after its first element and comma
+    fn tuple(
+        &self,
+        first: Expr<'token, 'lexeme>,
+        paren: &'token Token<'lexeme>,
+    ) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let mut elements = vec![first];
+        while !self.check(TokenType::RightParen) {
+            elements.push(self.parse_precedence(Precedence::Assignment)?);
+            if !self.match_token_types(&vec![TokenType::Comma]) {
+                break;
+            }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after tuple elements")?;
+        Ok(Expr::Tuple(elements, paren))
+    }
+
+    // "a${x}b${y}c" is scanned as InterpolationStart("a") x InterpolationSegment("b") y InterpolationEnd("c")
+    fn interpolation(&self) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let mut parts = vec![self.literal()?];
+
+        loop {
+            parts.push(self.expression()?);
+            if self.check(TokenType::InterpolationSegment) {
+                self.advance();
+                parts.push(self.literal()?);
+            } else {
+                let end = self.consume(
+                    TokenType::InterpolationEnd,
+                    "Expect '}' after interpolated expression",
+                )?;
+                parts.push(Expr::Literal(end.literal.as_ref().unwrap().into()));
+                return Ok(Expr::Interpolation(parts));
+            }
+        }
     }
 
     fn unary(&self) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
-        if self.match_token_types(&vec![TokenType::Bang, TokenType::Minus]) {
-            let operator = self.prev();
-            let right = self.unary()?;
-            return Ok(Expr::Unary(operator, Box::new(right)));
-        }
-
-        Ok(self.primary()?)
-    }
-
-    fn primary(&self) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
-        if self.match_token_types(&vec![TokenType::False]) {
-            Ok(Expr::Literal(Value::Boolean(false)))
-        } else if self.match_token_types(&vec![TokenType::True]) {
-            Ok(Expr::Literal(Value::Boolean(true)))
-        } else if self.match_token_types(&vec![TokenType::Nil]) {
-            Ok(Expr::Literal(Value::Nil))
-        } else if self.match_token_types(&vec![TokenType::Number, TokenType::String]) {
-            Ok(Expr::Literal(
-                (self.prev().literal.as_ref().unwrap()).into(),
-            ))
+        let operator = self.prev();
+        let right = self.parse_precedence(Precedence::Unary)?;
+        Ok(Expr::Unary(operator, Box::new(right)))
+    }
+
+    fn binary(
+        &self,
+        left: Expr<'token, 'lexeme>,
+    ) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let operator = self.prev();
+        let right = self.parse_precedence(self.right_operand_precedence())?;
+        // a * b + c adds c to the result of a * b, which is what evaluating the chain in order does
+        match left {
+            Expr::Binary(first, mut operations) => {
+                operations.push((operator, right));
+                Ok(Expr::Binary(first, operations))
+            }
+            left => Ok(Expr::Binary(Box::new(left), vec![(operator, right)])),
+        }
+    }
+
+    fn comma(
+        &self,
+        left: Expr<'token, 'lexeme>,
+    ) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let comma = self.prev();
+        let right = self.parse_precedence(self.right_operand_precedence())?;
+        let mut exprs = match left {
+            Expr::Comma(exprs) => exprs,
+            left => vec![left],
+        };
+
+        match right {
+            Expr::Assign(target, operator, value)
+                if operator.token_type == TokenType::Equal
+                    && exprs.last().is_some_and(Self::is_place) =>
+            {
+                // the places right before the = are assigned along with its own target
+                let places = exprs
+                    .iter()
+                    .rposition(|expr| !Self::is_place(expr))
+                    .map_or(0, |i| i + 1);
+                let targets = exprs.split_off(places);
+                let assign = self.destructuring(targets, target, operator, *value, comma)?;
+                if exprs.is_empty() {
+                    return Ok(assign);
+                }
+                exprs.push(assign);
+            }
+            right => exprs.push(right),
+        }
+        Ok(Expr::Comma(exprs))
+    }
+
+    // a, b = b, a assigns the values after the = to the targets before it, a, b = pair unpacks a
+    // single tuple or list
+    // one value is taken per target, so the comma list may go on after them, e.g. a, b = b, a, a + b
+    // a mismatch is reported at the comma before the =
+    fn destructuring(
+        &self,
+        exprs: Vec<Expr<'token, 'lexeme>>,
+        last: Target<'token, 'lexeme>,
+        operator: &'token Token<'lexeme>,
+        first_value: Expr<'token, 'lexeme>,
+        comma: &'token Token<'lexeme>,
+    ) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let mut targets = Vec::with_capacity(exprs.len() + 1);
+        for expr in exprs {
+            targets.push(self.assignment_target(expr, operator)?);
+        }
+        targets.push(last);
+
+        let mut values = vec![first_value];
+        while values.len() < targets.len() && self.match_token_types(&vec![TokenType::Comma]) {
+            values.push(self.parse_precedence(Precedence::Assignment)?);
+        }
+        let value = if values.len() == 1 {
+            values.pop().unwrap()
+        } else {
+            Expr::Tuple(values, operator)
+        };
+        Ok(Expr::Assign(
+            Target::Tuple(targets, comma),
+            operator,
+            Box::new(value),
+        ))
+    }
+
+    // an expression that could be assigned to
+    fn is_place(expr: &Expr<'token, 'lexeme>) -> bool {
+        match expr {
+            Expr::Variable(_) | Expr::Index(..) | Expr::Get(..) => true,
+            Expr::Tuple(elements, _) => elements.iter().all(Self::is_place),
+            _ => false,
+        }
+    }
+
+    // right-associative: a ? b : c ? d : e is parsed as a ? b : (c ? d : e)
+    fn ternary(
+        &self,
+        condition: Expr<'token, 'lexeme>,
+    ) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let then_branch = self.expression()?;
+        self.consume(
+            TokenType::Colon,
+            "Expect ':' after then branch of conditional expression",
+        )?;
+        let else_branch = self.parse_precedence(Precedence::Ternary)?;
+        Ok(Expr::Ternary(
+            Box::new(condition),
+            Box::new(then_branch),
+            Box::new(else_branch),
+        ))
+    }
+
+    fn list(&self) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let mut elements = Vec::new();
+        if !self.check(TokenType::RightBracket) {
+            loop {
+                elements.push(self.parse_precedence(Precedence::Assignment)?);
+                if !self.match_token_types(&vec![TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightBracket, "Expect ']' after list elements")?;
+        Ok(Expr::List(elements))
+    }
+
+    fn map(&self) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let mut entries = Vec::new();
+        if !self.check(TokenType::RightBrace) {
+            loop {
+                let key = self.parse_precedence(Precedence::Assignment)?;
+                let colon = self.consume(TokenType::Colon, "Expect ':' after map key")?;
+                let value = self.parse_precedence(Precedence::Assignment)?;
+                entries.push((key, colon, value));
+                if !self.match_token_types(&vec![TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after map entries")?;
+        Ok(Expr::Map(entries))
+    }
+
+    // match (value) { pattern [if guard] => body, ... }
+    fn match_expression(&self) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let keyword = self.prev();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'match'")?;
+        // parsed like any parenthesized expression, so match (a, b) matches the tuple (a, b)
+        let scrutinee = self.grouping()?;
+        self.consume(TokenType::LeftBrace, "Expect '{' before match arms")?;
+
+        let mut arms = Vec::new();
+        while !self.check(TokenType::RightBrace) {
+            arms.push(self.match_arm()?);
+            if !self.match_token_types(&vec![TokenType::Comma]) {
+                break;
+            }
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after match arms")?;
+
+        Ok(Expr::Match(Box::new(scrutinee), keyword, arms))
+    }
+
+    // for (name in iterable) body
+    fn for_loop(&self) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        self.labelled_loop(None)
+    }
+
+    fn labelled_loop(
+        &self,
+        label: Option<&'token Token<'lexeme>>,
+    ) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        if let Some(label) = label.filter(|label| self.is_loop_label(label)) {
+            return Err(ParseError {
+                token: label.deep_clone(),
+                msg: "An enclosing loop already has this label".into(),
+            });
+        }
+        self.consume(TokenType::LeftParen, "Expect '(' after 'for'")?;
+        let name = self.consume(TokenType::Identifier, "Expect loop variable name")?;
+        let keyword = self.consume(TokenType::In, "Expect 'in' after loop variable")?;
+        let iterable = self.parse_precedence(Precedence::Assignment)?;
+        self.consume(TokenType::RightParen, "Expect ')' after loop iterable")?;
+
+        self.loops.borrow_mut().push(label);
+        let body = self.parse_precedence(Precedence::Assignment);
+        self.loops.borrow_mut().pop();
+        Ok(Expr::For(
+            label,
+            name,
+            keyword,
+            Box::new(iterable),
+            Box::new(body?),
+        ))
+    }
+
+    // break or continue, optionally followed by the label of the loop it applies to
+    fn jump(&self) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let keyword = self.prev();
+        let label = if self.check(TokenType::Identifier) {
+            Some(self.advance())
+        } else {
+            None
+        };
+        match label {
+            None if self.loops.borrow().is_empty() => {
+                return Err(ParseError {
+                    token: keyword.deep_clone(),
+                    msg: format!(
+                        "Can't use '{}' outside of a loop",
+                        String::from_utf8_lossy(&keyword.lexeme)
+                    ),
+                })
+            }
+            Some(label) if !self.is_loop_label(label) => {
+                return Err(ParseError {
+                    token: label.deep_clone(),
+                    msg: "No enclosing loop has this label".into(),
+                })
+            }
+            _ => (),
+        }
+        if keyword.token_type == TokenType::Break {
+            Ok(Expr::Break(keyword, label))
+        } else {
+            Ok(Expr::Continue(keyword, label))
+        }
+    }
+
+    // throw value, which binds like an assignment: throw a, b throws a
+    fn throw(&self) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let keyword = self.prev();
+        let value = self.parse_precedence(Precedence::Assignment)?;
+        Ok(Expr::Throw(keyword, Box::new(value)))
+    }
+
+    // try { body } catch (name) { handler } finally { block }, with either or both of the last two
+    fn try_expression(&self) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let body = self.block("Expect '{' after 'try'", "Expect '}' after try block")?;
+        let catch = if self.match_token_types(&vec![TokenType::Catch]) {
+            self.consume(TokenType::LeftParen, "Expect '(' after 'catch'")?;
+            let name = self.consume(TokenType::Identifier, "Expect catch variable name")?;
+            self.consume(TokenType::RightParen, "Expect ')' after catch variable")?;
+            let handler = self.block(
+                "Expect '{' after catch variable",
+                "Expect '}' after catch block",
+            )?;
+            Some((name, Box::new(handler)))
+        } else {
+            None
+        };
+        let finally = if self.match_token_types(&vec![TokenType::Finally]) {
+            Some(Box::new(self.block(
+                "Expect '{' after 'finally'",
+                "Expect '}' after finally block",
+            )?))
+        } else {
+            None
+        };
+        if catch.is_none() && finally.is_none() {
+            return Err(ParseError {
+                token: self.peek().deep_clone(),
+                msg: "Expect 'catch' or 'finally' after try block".into(),
+            });
+        }
+        Ok(Expr::Try(Box::new(body), catch, finally))
+    }
+
+    // { expression }, an empty block is nil
+    fn block(
+        &self,
+        open: &'static str,
+        close: &'static str,
+    ) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        self.consume(TokenType::LeftBrace, open)?;
+        if self.match_token_types(&vec![TokenType::RightBrace]) {
+            return Ok(Expr::Literal(Value::Nil));
+        }
+        let expr = self.parse_precedence(Precedence::Comma)?;
+        self.consume(TokenType::RightBrace, close)?;
+        Ok(expr)
+    }
+
+    fn is_loop_label(&self, name: &Token<'lexeme>) -> bool {
+        self.loops
+            .borrow()
+            .iter()
+            .any(|label| label.is_some_and(|label| label.lexeme == name.lexeme))
+    }
+
+    fn match_arm(&self) -> Result<MatchArm<'token, 'lexeme>, ParseError<'err>> {
+        let pattern = self.pattern()?;
+        let guard = if self.match_token_types(&vec![TokenType::If]) {
+            let enclosing = self.guard_end.replace(self.guard_end());
+            let guard = self.parse_precedence(Precedence::Assignment);
+            self.guard_end.set(enclosing);
+            Some(guard?)
+        } else {
+            None
+        };
+        self.consume(TokenType::FatArrow, "Expect '=>' after match pattern")?;
+        let body = self.parse_precedence(Precedence::Assignment)?;
+        Ok(MatchArm {
+            pattern,
+            guard,
+            body,
+        })
+    }
+
+    // the first '=>' after the 'if' of a guard that isn't inside brackets of the guard
+    fn guard_end(&self) -> Option<usize> {
+        let mut depth = 0usize;
+        for (i, token) in self.tokens.iter().enumerate().skip(self.curr.get()) {
+            match token.token_type {
+                TokenType::LeftParen
+                | TokenType::LeftBracket
+                | TokenType::LeftBrace
+                | TokenType::InterpolationStart => depth += 1,
+                TokenType::RightParen
+                | TokenType::RightBracket
+                | TokenType::RightBrace
+                | TokenType::InterpolationEnd => depth = depth.checked_sub(1)?,
+                TokenType::FatArrow if depth == 0 => return Some(i),
+                _ => (),
+            }
+        }
+        None
+    }
+
+    // patterns share the expression nesting limit
+    fn pattern(&self) -> Result<Pattern, ParseError<'err>> {
+        let depth = self.depth.get();
+        let pattern = self.enter_nesting().and_then(|()| self.nested_pattern());
+        self.depth.set(depth);
+        pattern
+    }
+
+    fn nested_pattern(&self) -> Result<Pattern, ParseError<'err>> {
+        if self.match_token_types(&vec![TokenType::LeftBracket]) {
+            self.list_pattern()
         } else if self.match_token_types(&vec![TokenType::LeftParen]) {
-            let expr = self.expression()?;
-            self.consume(TokenType::RightParen, "Expect ')' after expression")?;
-            Ok(Expr::Grouping(Box::new(expr)))
+            self.tuple_pattern()
+        } else if self.match_token_types(&vec![TokenType::LeftBrace]) {
+            self.map_pattern()
+        } else if self.check(TokenType::Identifier) && *self.peek().lexeme == *b"_" {
+            self.advance();
+            Ok(Pattern::Wildcard)
         } else {
-            Err(ParseError {
+            self.range_pattern()
+        }
+    }
+
+    fn list_pattern(&self) -> Result<Pattern, ParseError<'err>> {
+        let patterns = self.patterns(TokenType::RightBracket)?;
+        self.consume(TokenType::RightBracket, "Expect ']' after list pattern")?;
+        Ok(Pattern::List(patterns))
+    }
+
+    fn tuple_pattern(&self) -> Result<Pattern, ParseError<'err>> {
+        let patterns = self.patterns(TokenType::RightParen)?;
+        self.consume(TokenType::RightParen, "Expect ')' after tuple pattern")?;
+        Ok(Pattern::Tuple(patterns))
+    }
+
+    fn map_pattern(&self) -> Result<Pattern, ParseError<'err>> {
+        let mut entries = Vec::new();
+        while !self.check(TokenType::RightBrace) {
+            let key = self.literal_pattern()?;
+            self.consume(TokenType::Colon, "Expect ':' after map pattern key")?;
+            entries.push((key, self.pattern()?));
+            if !self.match_token_types(&vec![TokenType::Comma]) {
+                break;
+            }
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after map pattern")?;
+        Ok(Pattern::Map(entries))
+    }
+
+    // a literal, or a range of numbers when followed by ..
+    fn range_pattern(&self) -> Result<Pattern, ParseError<'err>> {
+        let start_token = self.peek();
+        let start = self.literal_pattern()?;
+        if !self.match_token_types(&vec![TokenType::DotDot]) {
+            return Ok(Pattern::Literal(start));
+        }
+        let end = self.literal_pattern()?;
+        match (start, end) {
+            (Value::Number(start), Value::Number(end)) => Ok(Pattern::Range(start, end)),
+            _ => Err(ParseError {
+                token: start_token.deep_clone(),
+                msg: "Range pattern bounds must be numbers".into(),
+            }),
+        }
+    }
+
+    // comma separated patterns up to (not including) the closing token, a trailing comma is allowed
+    fn patterns(&self, closing: TokenType) -> Result<Vec<Pattern>, ParseError<'err>> {
+        let mut patterns = Vec::new();
+        while !self.check(closing) {
+            patterns.push(self.pattern()?);
+            if !self.match_token_types(&vec![TokenType::Comma]) {
+                break;
+            }
+        }
+        Ok(patterns)
+    }
+
+    fn literal_pattern(&self) -> Result<Value, ParseError<'err>> {
+        let negative = self.match_token_types(&vec![TokenType::Minus]);
+        if negative {
+            self.consume(TokenType::Number, "Expect number after '-' in pattern")?;
+        } else if !self.match_token_types(&vec![
+            TokenType::False,
+            TokenType::True,
+            TokenType::Nil,
+            TokenType::Number,
+            TokenType::String,
+        ]) {
+            return Err(ParseError {
                 token: self.peek().deep_clone(),
-                msg: "Expect expression",
-            })
+                msg: "Expect pattern".into(),
+            });
+        }
+
+        match Self::literal_value(self.prev()) {
+            Value::Number(n) if negative => Ok(Value::Number(-n)),
+            value => Ok(value),
+        }
+    }
+
+    // positional arguments, then named ones: f(1, 2, c: 3)
+    fn call(
+        &self,
+        callee: Expr<'token, 'lexeme>,
+    ) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let mut arguments = Vec::new();
+        let mut named: Vec<(&Token, Expr)> = Vec::new();
+        while !self.check(TokenType::RightParen) {
+            if self.is_named_argument() {
+                let name = self.advance();
+                self.advance();
+                if named.iter().any(|(other, _)| other.lexeme == name.lexeme) {
+                    return Err(ParseError {
+                        token: name.deep_clone(),
+                        msg: "Duplicate named argument".into(),
+                    });
+                }
+                named.push((name, self.parse_precedence(Precedence::Assignment)?));
+            } else if named.is_empty() {
+                arguments.push(self.parse_precedence(Precedence::Assignment)?);
+            } else {
+                return Err(ParseError {
+                    token: self.peek().deep_clone(),
+                    msg: "Expect named argument after named arguments".into(),
+                });
+            }
+            if !self.match_token_types(&vec![TokenType::Comma]) {
+                break;
+            }
+        }
+        let paren = self.consume(TokenType::RightParen, "Expect ')' after arguments")?;
+        Ok(Expr::Call(Box::new(callee), paren, arguments, named))
+    }
+
+    // name: value, unless the name labels a loop
+    fn is_named_argument(&self) -> bool {
+        self.check(TokenType::Identifier)
+            && self.check_next(TokenType::Colon)
+            && self
+                .tokens
+                .get(self.curr.get() + 2)
+                .is_none_or(|token| token.token_type != TokenType::For)
+    }
+
+    fn property(
+        &self,
+        object: Expr<'token, 'lexeme>,
+    ) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let name = self.consume(TokenType::Identifier, "Expect property name after '.'")?;
+        Ok(Expr::Get(Box::new(object), name))
+    }
+
+    // xs[i] or xs[start:end] where either bound of a slice may be omitted
+    fn index(
+        &self,
+        object: Expr<'token, 'lexeme>,
+    ) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let bracket = self.prev();
+        let start = if self.check(TokenType::Colon) {
+            None
+        } else {
+            Some(Box::new(self.parse_precedence(Precedence::Assignment)?))
+        };
+
+        if self.match_token_types(&vec![TokenType::Colon]) {
+            let end = if self.check(TokenType::RightBracket) {
+                None
+            } else {
+                Some(Box::new(self.parse_precedence(Precedence::Assignment)?))
+            };
+            self.consume(TokenType::RightBracket, "Expect ']' after slice")?;
+            return Ok(Expr::Slice(Box::new(object), bracket, start, end));
         }
+
+        self.consume(TokenType::RightBracket, "Expect ']' after index")?;
+        Ok(Expr::Index(Box::new(object), bracket, start.unwrap()))
+    }
+
+    // handles both = and the compound assignment operators
+    fn assignment(
+        &self,
+        target: Expr<'token, 'lexeme>,
+    ) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let operator = self.prev();
+        let value = self.parse_precedence(self.right_operand_precedence())?;
+        let target = self.assignment_target(target, operator)?;
+        Ok(Expr::Assign(target, operator, Box::new(value)))
+    }
+
+    // ++x is desugared to x += 1
+    fn prefix_increment(&self) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let operator = self.prev();
+        let target = self.parse_precedence(Precedence::Unary)?;
+        let target = self.assignment_target(target, operator)?;
+        Ok(Expr::Assign(
+            target,
+            operator,
+            Box::new(Expr::Literal(Value::Number(1.0))),
+        ))
+    }
+
+    fn postfix_increment(
+        &self,
+        target: Expr<'token, 'lexeme>,
+    ) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let operator = self.prev();
+        let target = self.assignment_target(target, operator)?;
+        Ok(Expr::Postfix(target, operator))
+    }
+
+    fn assignment_target(
+        &self,
+        expr: Expr<'token, 'lexeme>,
+        operator: &'token Token<'lexeme>,
+    ) -> Result<Target<'token, 'lexeme>, ParseError<'err>> {
+        match expr {
+            Expr::Variable(name) => Ok(Target::Variable(name)),
+            Expr::Index(object, bracket, index) => Ok(Target::Index(object, bracket, index)),
+            Expr::Get(object, name) => Ok(Target::Field(object, name)),
+            // (a, b) = (b, a), only plain assignment can destructure
+            Expr::Tuple(elements, paren) if operator.token_type == TokenType::Equal => {
+                let mut targets = Vec::with_capacity(elements.len());
+                for element in elements {
+                    targets.push(self.assignment_target(element, operator)?);
+                }
+                Ok(Target::Tuple(targets, paren))
+            }
+            _ => Err(ParseError {
+                token: operator.deep_clone(),
+                msg: "Invalid assignment target".into(),
+            }),
+        }
+    }
+
+    // error production: a binary operator at the start of an expression
+    // the right operand is parsed (with the operator's precedence) and discarded so that parsing resumes after it
+    fn missing_left_operand(&self) -> Result<Expr<'token, 'lexeme>, ParseError<'err>> {
+        let operator = self.prev();
+        let _ = self.parse_precedence(self.right_operand_precedence());
+
+        Err(ParseError {
+            token: operator.deep_clone(),
+            msg: format!(
+                "binary operator '{}' is missing its left operand",
+                String::from_utf8_lossy(&operator.lexeme)
+            ),
+        })
     }
 
     fn match_token_types(&self, token_types: &Vec<TokenType>) -> bool {
@@ -157,11 +1130,18 @@ impl<'token, 'lexeme, 'err> Parser<'token, 'lexeme, 'err> {
         } else {
             Err(ParseError {
                 token: self.peek().deep_clone(),
-                msg,
+                msg: msg.into(),
             })
         }
     }
 
+    fn check_next(&self, token_type: TokenType) -> bool {
+        match self.tokens.get(self.curr.get() + 1) {
+            Some(token) => token.token_type == token_type,
+            None => false,
+        }
+    }
+
     fn is_at_end(&self) -> bool {
         self.peek().token_type == TokenType::EOF
     }
@@ -0,0 +1,79 @@
+use crate::error::LoxError;
+use crate::expr::{Expr, Function};
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::sync::Shared;
+use crate::token::Token;
+
+use self_cell::self_cell;
+
+type TokenList<'lexeme> = Vec<Token<'lexeme>>;
+type Tree<'a> = Expr<'a, 'a>;
+type FunctionTable<'a> = Vec<&'a Function<'a, 'a>>;
+
+self_cell!(
+    // the source and the tokens scanned from it
+    struct Tokens {
+        owner: Box<str>,
+        #[covariant]
+        dependent: TokenList,
+    }
+);
+
+self_cell!(
+    // the tokens and the expression parsed from them
+    struct Parsed {
+        owner: Tokens,
+        #[covariant]
+        dependent: Tree,
+    }
+);
+
+self_cell!(
+    // the parsed expression and the function expressions in it, by id
+    struct Indexed {
+        owner: Parsed,
+        #[covariant]
+        dependent: FunctionTable,
+    }
+);
+
+// a parsed script that any interpreter can run any number of times
+// it owns its source and tokens, which are freed with the last clone of it, and can be shared
+// across threads with the `sync` feature
+// cloning is cheap, functions created by the script hold on to a clone
+#[derive(Clone)]
+pub struct Program {
+    indexed: Shared<Indexed>,
+}
+
+impl Program {
+    pub fn compile(source: &str) -> Result<Self, LoxError<'static>> {
+        let tokens = Tokens::try_new(source.into(), |source| Scanner::new(source).scan_tokens())?;
+        let parsed = Parsed::try_new(tokens, |tokens| {
+            Parser::new(tokens.borrow_dependent()).parse()
+        })?;
+        let indexed = Indexed::new(parsed, |parsed| {
+            let mut functions = Vec::new();
+            parsed.borrow_dependent().functions(&mut functions);
+            functions.sort_by_key(|function| function.id);
+            functions
+        });
+        Ok(Self {
+            indexed: Shared::new(indexed),
+        })
+    }
+
+    // print in prefix notation
+    pub fn pretty_print(&self) -> String {
+        self.expr().pretty_print()
+    }
+
+    pub(crate) fn expr(&self) -> &Expr<'_, '_> {
+        self.indexed.borrow_owner().borrow_dependent()
+    }
+
+    pub(crate) fn function(&self, id: usize) -> &Function<'_, '_> {
+        self.indexed.borrow_dependent()[id]
+    }
+}
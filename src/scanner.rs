@@ -11,6 +11,11 @@ pub struct Scanner<'lexeme> {
     start: usize,
     current: usize,
     line: usize,
+    line_start: usize,
+    start_line: usize,
+    start_column: usize,
+    // open brace count of every unfinished ${...}, innermost last
+    interpolations: Vec<usize>,
     errors: Vec<LexError>,
 }
 
@@ -22,6 +27,10 @@ impl<'lexeme, 'err> Scanner<'lexeme> {
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            start_line: 1,
+            start_column: 1,
+            interpolations: Vec::new(),
             errors: Vec::new(),
         }
     }
@@ -31,19 +40,27 @@ impl<'lexeme, 'err> Scanner<'lexeme> {
         HASHMAP.get_or_init(|| {
             let mut keywords = HashMap::new();
             keywords.insert("and", TokenType::And);
+            keywords.insert("break", TokenType::Break);
+            keywords.insert("catch", TokenType::Catch);
             keywords.insert("class", TokenType::Class);
+            keywords.insert("continue", TokenType::Continue);
             keywords.insert("else", TokenType::Else);
             keywords.insert("false", TokenType::False);
+            keywords.insert("finally", TokenType::Finally);
             keywords.insert("for", TokenType::For);
             keywords.insert("fun", TokenType::Fun);
             keywords.insert("if", TokenType::If);
+            keywords.insert("in", TokenType::In);
+            keywords.insert("match", TokenType::Match);
             keywords.insert("nil", TokenType::Nil);
             keywords.insert("or", TokenType::Or);
             keywords.insert("print", TokenType::Print);
             keywords.insert("return", TokenType::Return);
             keywords.insert("super", TokenType::Super);
             keywords.insert("this", TokenType::This);
+            keywords.insert("throw", TokenType::Throw);
             keywords.insert("true", TokenType::True);
+            keywords.insert("try", TokenType::Try);
             keywords.insert("var", TokenType::Var);
             keywords.insert("while", TokenType::While);
             keywords
@@ -53,11 +70,22 @@ impl<'lexeme, 'err> Scanner<'lexeme> {
     pub fn scan_tokens(mut self) -> Result<Vec<Token<'lexeme>>, LoxError<'err>> {
         while !self.is_at_end() {
             self.start = self.current;
+            self.start_line = self.line;
+            self.start_column = self.start - self.line_start + 1;
             self.scan_token();
         }
 
-        self.tokens
-            .push(Token::new(TokenType::EOF, b"", Option::None, self.line));
+        if !self.interpolations.is_empty() {
+            self.error("Unterminated string interpolation");
+        }
+
+        self.tokens.push(Token::new(
+            TokenType::EOF,
+            b"",
+            Option::None,
+            self.line,
+            self.current - self.line_start + 1,
+        ));
 
         if self.errors.is_empty() {
             Ok(self.tokens)
@@ -71,14 +99,70 @@ impl<'lexeme, 'err> Scanner<'lexeme> {
         match c {
             b'(' => self.add_token(TokenType::LeftParen, Option::None),
             b')' => self.add_token(TokenType::RightParen, Option::None),
-            b'{' => self.add_token(TokenType::LeftBrace, Option::None),
-            b'}' => self.add_token(TokenType::RightBrace, Option::None),
+            b'{' => {
+                if let Some(open_braces) = self.interpolations.last_mut() {
+                    *open_braces += 1;
+                }
+                self.add_token(TokenType::LeftBrace, Option::None)
+            }
+            b'}' => match self.interpolations.last_mut() {
+                Some(0) => {
+                    // end of an interpolated expression, the string literal continues
+                    self.interpolations.pop();
+                    self.string(TokenType::InterpolationSegment, TokenType::InterpolationEnd)
+                }
+                Some(open_braces) => {
+                    *open_braces -= 1;
+                    self.add_token(TokenType::RightBrace, Option::None)
+                }
+                None => self.add_token(TokenType::RightBrace, Option::None),
+            },
+            b'[' => self.add_token(TokenType::LeftBracket, Option::None),
+            b']' => self.add_token(TokenType::RightBracket, Option::None),
             b',' => self.add_token(TokenType::Comma, Option::None),
-            b'.' => self.add_token(TokenType::Dot, Option::None),
-            b'-' => self.add_token(TokenType::Minus, Option::None),
-            b'+' => self.add_token(TokenType::Plus, Option::None),
+            b'.' => {
+                let token_type = if self.match_char(b'.') {
+                    if self.match_char(b'.') {
+                        TokenType::DotDotDot
+                    } else {
+                        TokenType::DotDot
+                    }
+                } else {
+                    TokenType::Dot
+                };
+                self.add_token(token_type, Option::None)
+            }
+            b'-' => {
+                let token_type = if self.match_char(b'-') {
+                    TokenType::MinusMinus
+                } else if self.match_char(b'=') {
+                    TokenType::MinusEqual
+                } else {
+                    TokenType::Minus
+                };
+                self.add_token(token_type, Option::None)
+            }
+            b'+' => {
+                let token_type = if self.match_char(b'+') {
+                    TokenType::PlusPlus
+                } else if self.match_char(b'=') {
+                    TokenType::PlusEqual
+                } else {
+                    TokenType::Plus
+                };
+                self.add_token(token_type, Option::None)
+            }
             b';' => self.add_token(TokenType::Semicolon, Option::None),
-            b'*' => self.add_token(TokenType::Star, Option::None),
+            b'*' => {
+                let token_type = if self.match_char(b'=') {
+                    TokenType::StarEqual
+                } else {
+                    TokenType::Star
+                };
+                self.add_token(token_type, Option::None)
+            }
+            b'?' => self.add_token(TokenType::Question, Option::None),
+            b':' => self.add_token(TokenType::Colon, Option::None),
             b'!' => {
                 let token_type = if self.match_char(b'=') {
                     TokenType::BangEqual
@@ -90,6 +174,8 @@ impl<'lexeme, 'err> Scanner<'lexeme> {
             b'=' => {
                 let token_type = if self.match_char(b'=') {
                     TokenType::EqualEqual
+                } else if self.match_char(b'>') {
+                    TokenType::FatArrow
                 } else {
                     TokenType::Equal
                 };
@@ -116,22 +202,34 @@ impl<'lexeme, 'err> Scanner<'lexeme> {
                     while self.peek() != Some(b'\n') {
                         self.advance();
                     }
+                } else if self.match_char(b'=') {
+                    self.add_token(TokenType::SlashEqual, None);
                 } else {
                     self.add_token(TokenType::Slash, None);
                 }
             }
             b' ' | b'\r' | b'\t' => (),
-            b'\n' => self.line += 1,
-            b'"' => self.string(),
+            b'\n' => self.newline(),
+            b'"' => self.string(TokenType::InterpolationStart, TokenType::String),
             b'0'..=b'9' => self.number(),
             b'a'..=b'z' | b'A'..=b'Z' | b'_' => self.identifier(),
-            _ => self.errors.push(LexError {
-                line: self.line,
-                msg: "Unexpected character",
-            }),
+            _ => self.error("Unexpected character"),
         }
     }
 
+    fn error(&mut self, msg: &'static str) {
+        self.errors.push(LexError {
+            line: self.start_line,
+            column: self.start_column,
+            msg,
+        });
+    }
+
+    fn newline(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
+    }
+
     fn is_at_end(&self) -> bool {
         self.current >= self.source.len()
     }
@@ -143,8 +241,13 @@ impl<'lexeme, 'err> Scanner<'lexeme> {
 
     fn add_token(&mut self, token_type: TokenType, literal: Option<Literal<'lexeme>>) {
         let text = &self.source[self.start..self.current];
-        self.tokens
-            .push(Token::new(token_type, text, literal, self.line));
+        self.tokens.push(Token::new(
+            token_type,
+            text,
+            literal,
+            self.start_line,
+            self.start_column,
+        ));
     }
 
     fn match_char(&mut self, expected: u8) -> bool {
@@ -172,27 +275,43 @@ impl<'lexeme, 'err> Scanner<'lexeme> {
         }
     }
 
-    fn string(&mut self) {
-        while self.peek() != Some(b'"') {
-            if self.peek() == Some(b'\n') {
-                self.line += 1;
+    // scans string contents up to the closing '"' (emitting `end`) or up to a "${" (emitting `interpolation`)
+    // the opening delimiter ('"' or the '}' closing an interpolated expression) is already consumed
+    fn string(&mut self, interpolation: TokenType, end: TokenType) {
+        loop {
+            match self.peek() {
+                None => {
+                    self.error("Unterminated string");
+                    return;
+                }
+                Some(b'"') => break,
+                Some(b'$') if self.peek_next() == Some(b'{') => {
+                    self.advance(); // $
+                    self.advance(); // {
+                    self.interpolations.push(0);
+                    let value = &self.source[self.start + 1..self.current - 2];
+                    self.add_token(
+                        interpolation,
+                        Some(Literal::String(str::from_utf8(value).unwrap().into())),
+                    );
+                    return;
+                }
+                Some(b'\n') => {
+                    self.advance();
+                    self.newline();
+                }
+                Some(_) => {
+                    self.advance();
+                }
             }
-            self.advance();
-        }
-
-        if self.is_at_end() {
-            self.errors.push(LexError {
-                line: self.line,
-                msg: "Unterminated string",
-            });
         }
 
         self.advance(); // closing "
 
         let value = &self.source[self.start + 1..self.current - 1];
         self.add_token(
-            TokenType::String,
-            Some(Literal::String(str::from_utf8(value).unwrap())),
+            end,
+            Some(Literal::String(str::from_utf8(value).unwrap().into())),
         )
     }
 
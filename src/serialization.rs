@@ -0,0 +1,159 @@
+use crate::sync::{Lock, Shared};
+use crate::value::{Ancestors, Map, Value};
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, Serialize, SerializeMap, SerializeSeq, Serializer};
+use std::fmt;
+
+// largest integer an f64 holds exactly
+const MAX_SAFE_INTEGER: f64 = 9007199254740991.0;
+
+// tuples are written as sequences, natives, host objects and values that contain themselves can't be serialized
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Nested {
+            value: self,
+            ancestors: None,
+        }
+        .serialize(serializer)
+    }
+}
+
+// a value inside the lists and maps being serialized
+struct Nested<'a> {
+    value: &'a Value,
+    ancestors: Option<&'a Ancestors<'a, *const ()>>,
+}
+
+impl Serialize for Nested<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if let Some(id) = self.value.container_id() {
+            if Ancestors::contains(self.ancestors, id) {
+                return Err(ser::Error::custom(format!(
+                    "a {} that contains itself cannot be serialized",
+                    self.value.type_name()
+                )));
+            }
+        }
+        let frame = self
+            .value
+            .container_id()
+            .map(|id| Ancestors::new(id, self.ancestors));
+        let ancestors = frame.as_ref().or(self.ancestors);
+
+        match self.value {
+            // whole numbers are written as integers so that e.g. JSON gets 3 rather than 3.0
+            Value::Number(n) if n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER => {
+                serializer.serialize_i64(*n as i64)
+            }
+            Value::Number(n) => serializer.serialize_f64(*n),
+            Value::String(s) => serializer.serialize_str(s),
+            Value::Boolean(b) => serializer.serialize_bool(*b),
+            Value::Nil => serializer.serialize_unit(),
+            Value::List(list) => serialize_seq(&list.borrow(), ancestors, serializer),
+            Value::Tuple(tuple) => serialize_seq(tuple, ancestors, serializer),
+            Value::Map(map) => {
+                let map = map.borrow();
+                let mut state = serializer.serialize_map(Some(map.len()))?;
+                for (key, value) in map.iter() {
+                    // keys are hashable, so they can't contain a list or map
+                    state.serialize_entry(key, &Nested { value, ancestors })?;
+                }
+                state.end()
+            }
+            Value::NativeFunction(native) => Err(ser::Error::custom(format!(
+                "native function '{}' cannot be serialized",
+                native.name
+            ))),
+            Value::Function(closure) => Err(ser::Error::custom(format!(
+                "{} cannot be serialized",
+                closure
+            ))),
+            Value::HostObject(object) => Err(ser::Error::custom(format!(
+                "{} object cannot be serialized",
+                object.class_name()
+            ))),
+        }
+    }
+}
+
+fn serialize_seq<S: Serializer>(
+    values: &[Value],
+    ancestors: Option<&Ancestors<*const ()>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut state = serializer.serialize_seq(Some(values.len()))?;
+    for value in values {
+        state.serialize_element(&Nested { value, ancestors })?;
+    }
+    state.end()
+}
+
+// sequences become lists, and maps keep the order of their keys
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a number, string, boolean, nil, list or map")
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<Value, E> {
+        Ok(Value::Boolean(b))
+    }
+
+    fn visit_i64<E>(self, n: i64) -> Result<Value, E> {
+        Ok(Value::Number(n as f64))
+    }
+
+    fn visit_u64<E>(self, n: u64) -> Result<Value, E> {
+        Ok(Value::Number(n as f64))
+    }
+
+    fn visit_f64<E>(self, n: f64) -> Result<Value, E> {
+        Ok(Value::Number(n))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Value, E> {
+        Ok(Value::String(s.to_owned()))
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<Value, E> {
+        Ok(Value::String(s))
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Nil)
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Nil)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Value::List(Shared::new(Lock::new(values))))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut entries: A) -> Result<Value, A::Error> {
+        let mut map = Map::new();
+        while let Some((key, value)) = entries.next_entry()? {
+            map.insert(key, value).map_err(de::Error::custom)?;
+        }
+        Ok(Value::Map(Shared::new(Lock::new(map))))
+    }
+}
@@ -0,0 +1,50 @@
+use crate::sync::{DynBufRead, DynWrite, Shared, SyncLock};
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::sync::RwLockWriteGuard;
+
+// where an interpreter session writes output and diagnostics and reads input from
+// clones share the same streams, so natives can hold on to a handle and still see later replacements
+#[derive(Clone)]
+pub struct Streams {
+    output: Shared<SyncLock<Box<DynWrite>>>,
+    diagnostics: Shared<SyncLock<Box<DynWrite>>>,
+    input: Shared<SyncLock<Box<DynBufRead>>>,
+}
+
+// stdout, stderr and stdin
+impl Default for Streams {
+    fn default() -> Self {
+        Self {
+            output: Shared::new(SyncLock::new(Box::new(io::stdout()))),
+            diagnostics: Shared::new(SyncLock::new(Box::new(io::stderr()))),
+            input: Shared::new(SyncLock::new(Box::new(BufReader::new(io::stdin())))),
+        }
+    }
+}
+
+impl Streams {
+    pub fn output(&self) -> RwLockWriteGuard<'_, Box<dyn Write + Send + Sync>> {
+        self.output.borrow_mut()
+    }
+
+    pub fn diagnostics(&self) -> RwLockWriteGuard<'_, Box<dyn Write + Send + Sync>> {
+        self.diagnostics.borrow_mut()
+    }
+
+    pub fn input(&self) -> RwLockWriteGuard<'_, Box<dyn BufRead + Send + Sync>> {
+        self.input.borrow_mut()
+    }
+
+    pub fn set_output(&self, output: impl Write + Send + Sync + 'static) {
+        *self.output.borrow_mut() = Box::new(output);
+    }
+
+    pub fn set_diagnostics(&self, diagnostics: impl Write + Send + Sync + 'static) {
+        *self.diagnostics.borrow_mut() = Box::new(diagnostics);
+    }
+
+    pub fn set_input(&self, input: impl BufRead + Send + Sync + 'static) {
+        *self.input.borrow_mut() = Box::new(input);
+    }
+}
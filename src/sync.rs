@@ -0,0 +1,77 @@
+// shared ownership and interior mutability for values that scripts and natives hold on to
+// script values use Lock, a RefCell by default and an RwLock with the `sync` feature, which makes
+// values and whole interpreter sessions Send and Sync
+// natives, host objects and streams are Send and Sync with or without the feature, so turning it
+// on doesn't change what compiles
+
+use std::io::{BufRead, Write};
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+pub type Shared<T> = std::sync::Arc<T>;
+
+pub(crate) type DynWrite = dyn Write + Send + Sync;
+pub(crate) type DynBufRead = dyn BufRead + Send + Sync;
+
+// lock for the session state that natives capture, always thread safe
+// a panic while a lock was held doesn't make the value unusable for the rest of the session
+#[derive(Default)]
+pub struct SyncLock<T: ?Sized>(RwLock<T>);
+
+impl<T> SyncLock<T> {
+    pub fn new(value: T) -> Self {
+        Self(RwLock::new(value))
+    }
+}
+
+impl<T: ?Sized> SyncLock<T> {
+    pub fn borrow(&self) -> RwLockReadGuard<'_, T> {
+        self.0.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn borrow_mut(&self) -> RwLockWriteGuard<'_, T> {
+        self.0.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: ?Sized + std::fmt::Debug> std::fmt::Debug for SyncLock<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(not(feature = "sync"))]
+mod imp {
+    use std::cell::{Ref, RefCell, RefMut};
+
+    #[derive(Default)]
+    pub struct Lock<T: ?Sized>(RefCell<T>);
+
+    impl<T> Lock<T> {
+        pub fn new(value: T) -> Self {
+            Self(RefCell::new(value))
+        }
+    }
+
+    impl<T: ?Sized> Lock<T> {
+        pub fn borrow(&self) -> Ref<'_, T> {
+            self.0.borrow()
+        }
+
+        pub fn borrow_mut(&self) -> RefMut<'_, T> {
+            self.0.borrow_mut()
+        }
+    }
+
+    impl<T: ?Sized + std::fmt::Debug> std::fmt::Debug for Lock<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            self.0.fmt(f)
+        }
+    }
+}
+
+#[cfg(feature = "sync")]
+mod imp {
+    pub type Lock<T> = super::SyncLock<T>;
+}
+
+pub use imp::*;
@@ -1,3 +1,6 @@
+use std::borrow::Cow;
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum TokenType {
     // Single-character tokens
@@ -5,6 +8,8 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
     Dot,
     Minus,
@@ -12,8 +17,10 @@ pub enum TokenType {
     Semicolon,
     Slash,
     Star,
+    Question,
+    Colon,
 
-    // One or two character tokens
+    // One, two or three character tokens
     Bang,
     BangEqual,
     Equal,
@@ -22,27 +29,47 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PlusPlus,
+    MinusMinus,
+    FatArrow,
+    DotDot,
+    DotDotDot,
 
     // Literals
     Identifier,
     String,
     Number,
+    InterpolationStart,
+    InterpolationSegment,
+    InterpolationEnd,
 
     // Keywords
     And,
+    Break,
+    Catch,
     Class,
+    Continue,
     Else,
     False,
+    Finally,
     Fun,
     For,
     If,
+    In,
+    Match,
     Nil,
     Or,
     Print,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
 
@@ -55,12 +82,15 @@ impl std::fmt::Display for TokenType {
     }
 }
 
+// lexemes and string literals borrow from the source, or own their bytes once deep_clone has cut
+// them loose from it
 #[derive(Debug)]
 pub struct Token<'lexeme> {
     pub(crate) token_type: TokenType,
-    pub(crate) lexeme: &'lexeme [u8],
+    pub(crate) lexeme: Cow<'lexeme, [u8]>,
     pub(crate) literal: Option<Literal<'lexeme>>,
     pub(crate) line: usize,
+    pub(crate) column: usize,
 }
 
 impl<'lexeme> Token<'lexeme> {
@@ -69,12 +99,14 @@ impl<'lexeme> Token<'lexeme> {
         lexeme: &'lexeme [u8],
         literal: Option<Literal<'lexeme>>,
         line: usize,
+        column: usize,
     ) -> Self {
         Token {
             token_type,
-            lexeme,
+            lexeme: Cow::Borrowed(lexeme),
             literal,
             line,
+            column,
         }
     }
 
@@ -82,16 +114,17 @@ impl<'lexeme> Token<'lexeme> {
     pub fn deep_clone(&self) -> Token<'static> {
         Token {
             token_type: self.token_type,
-            lexeme: Box::leak(self.lexeme.to_vec().into_boxed_slice()),
+            lexeme: Cow::Owned(self.lexeme.to_vec()),
             literal: self.literal.as_ref().map(|lit| lit.deep_clone()),
             line: self.line,
+            column: self.column,
         }
     }
 }
 
 #[derive(Debug)]
 pub enum Literal<'lexeme> {
-    String(&'lexeme str),
+    String(Cow<'lexeme, str>),
     Number(f64),
 }
 
@@ -99,7 +132,7 @@ impl<'lexeme> Literal<'lexeme> {
     // used for error handling (tokens are dropped after run() function, but error handling is done in main())
     pub fn deep_clone(&self) -> Literal<'static> {
         match self {
-            Literal::String(s) => Literal::String(Box::leak(s.to_string().into_boxed_str())),
+            Literal::String(s) => Literal::String(Cow::Owned(s.to_string())),
             Literal::Number(n) => Literal::Number(*n),
         }
     }
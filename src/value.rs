@@ -1,29 +1,358 @@
+use crate::error::RuntimeError;
+use crate::expr::Function;
+use crate::interpreter::Scope;
+use crate::program::Program;
 use crate::token::Literal;
 
-#[derive(Debug)]
+use crate::sync::{Lock, Shared};
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
 pub enum Value {
     Number(f64),
     String(String),
     Boolean(bool),
     Nil,
+    // shared and mutable: copies of a list value alias the same elements
+    List(Shared<Lock<Vec<Value>>>),
+    // immutable, so copies may share the elements freely
+    Tuple(Shared<[Value]>),
+    Map(Shared<Lock<Map>>),
+    NativeFunction(Shared<NativeFunction>),
+    // function created by the script, copies share the same closure
+    Function(Shared<Closure>),
+    // Rust value exposed to scripts, copies share the same object
+    HostObject(Shared<dyn HostObject>),
 }
 
 impl From<&Literal<'_>> for Value {
     fn from(literal: &Literal<'_>) -> Self {
-        match *literal {
-            Literal::String(s) => Value::String(s.to_owned()),
-            Literal::Number(n) => Value::Number(n),
+        match literal {
+            Literal::String(s) => Value::String(s.to_string()),
+            Literal::Number(n) => Value::Number(*n),
         }
     }
 }
 
 impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.fmt_nested(f, None)
+    }
+}
+
+// the lists and maps a recursive walk over a value is currently inside of
+// a value can contain itself (xs[0] = xs), so walks check this chain before descending
+pub(crate) struct Ancestors<'a, T> {
+    id: T,
+    parent: Option<&'a Ancestors<'a, T>>,
+}
+
+impl<'a, T: PartialEq + Copy> Ancestors<'a, T> {
+    pub(crate) fn new(id: T, parent: Option<&'a Ancestors<'a, T>>) -> Self {
+        Self { id, parent }
+    }
+
+    pub(crate) fn contains(ancestors: Option<&Ancestors<'_, T>>, id: T) -> bool {
+        let mut ancestor = ancestors;
+        while let Some(current) = ancestor {
+            if current.id == id {
+                return true;
+            }
+            ancestor = current.parent;
+        }
+        false
+    }
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Boolean(_) => "boolean",
+            Value::Nil => "nil",
+            Value::List(_) => "list",
+            Value::Tuple(_) => "tuple",
+            Value::Map(_) => "map",
+            Value::NativeFunction(_) => "native function",
+            Value::Function(_) => "function",
+            Value::HostObject(_) => "object",
+        }
+    }
+
+    // bytes of a string or elements of a container, what the interpreter's value size limit applies to
+    pub fn size(&self) -> usize {
+        match self {
+            Value::String(s) => s.len(),
+            Value::List(list) => list.borrow().len(),
+            Value::Tuple(tuple) => tuple.len(),
+            Value::Map(map) => map.borrow().len(),
+            _ => 0,
+        }
+    }
+
+    // identity of a list or map, the only values that can end up containing themselves
+    pub(crate) fn container_id(&self) -> Option<*const ()> {
+        match self {
+            Value::List(list) => Some(Shared::as_ptr(list) as *const ()),
+            Value::Map(map) => Some(Shared::as_ptr(map) as *const ()),
+            _ => None,
+        }
+    }
+
+    // a list or map inside itself is printed as [...] or {...}
+    fn fmt_nested(
+        &self,
+        f: &mut std::fmt::Formatter,
+        ancestors: Option<&Ancestors<*const ()>>,
+    ) -> std::fmt::Result {
+        if let Some(id) = self.container_id() {
+            if Ancestors::contains(ancestors, id) {
+                return match self {
+                    Value::List(_) => write!(f, "[...]"),
+                    _ => write!(f, "{{...}}"),
+                };
+            }
+        }
+        let frame = self.container_id().map(|id| Ancestors::new(id, ancestors));
+        let ancestors = frame.as_ref().or(ancestors);
         match self {
             Value::Number(n) => write!(f, "{}", n),
             Value::String(s) => write!(f, "{}", s),
             Value::Boolean(b) => write!(f, "{}", b),
             Value::Nil => write!(f, "nil"),
+            Value::List(list) => {
+                write!(f, "[")?;
+                for (i, element) in list.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    element.fmt_element(f, ancestors)?;
+                }
+                write!(f, "]")
+            }
+            Value::Tuple(tuple) => {
+                write!(f, "(")?;
+                for (i, element) in tuple.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    element.fmt_element(f, ancestors)?;
+                }
+                // a one element tuple is written (x,) to tell it apart from a grouping
+                if tuple.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            Value::Map(map) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    key.fmt_element(f, ancestors)?;
+                    write!(f, ": ")?;
+                    value.fmt_element(f, ancestors)?;
+                }
+                write!(f, "}}")
+            }
+            Value::NativeFunction(native) => write!(f, "<native fn {}>", native.name),
+            Value::Function(closure) => write!(f, "{}", closure),
+            Value::HostObject(object) => write!(f, "<{} object>", object.class_name()),
         }
     }
+
+    // strings nested in other values are quoted so that e.g. ["a, b"] and ["a", "b"] print differently
+    fn fmt_element(
+        &self,
+        f: &mut std::fmt::Formatter,
+        ancestors: Option<&Ancestors<*const ()>>,
+    ) -> std::fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "\"{}\"", s),
+            _ => self.fmt_nested(f, ancestors),
+        }
+    }
+}
+
+pub trait NativeFn: Fn(&[Value]) -> Result<Value, RuntimeError<'static>> + Send + Sync {}
+
+impl<F> NativeFn for F where F: Fn(&[Value]) -> Result<Value, RuntimeError<'static>> + Send + Sync {}
+
+// function implemented in Rust, called with exactly `arity` arguments
+pub struct NativeFunction {
+    pub name: String,
+    pub arity: usize,
+    pub function: Box<dyn NativeFn>,
+}
+
+impl std::fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+// a function expression together with the variables that were in scope where it was evaluated
+// it keeps the program it was parsed from alive, so it can be called after that run has ended
+pub struct Closure {
+    pub(crate) program: Program,
+    // index of the function in the program, see Program::function
+    pub(crate) id: usize,
+    // None for a function created at the top level, which only sees the globals
+    pub(crate) scope: Option<Shared<Lock<Scope>>>,
+}
+
+impl Closure {
+    pub(crate) fn function(&self) -> &Function<'_, '_> {
+        self.program.function(self.id)
+    }
+}
+
+// functions have no names, so they are told apart by where they are written
+impl std::fmt::Display for Closure {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let token = self.function().token;
+        write!(
+            f,
+            "<fn anonymous [line: {}, column: {}]>",
+            token.line, token.column
+        )
+    }
+}
+
+impl std::fmt::Debug for Closure {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+// Rust type that scripts use through obj.field, obj.field = value and obj.method(...)
+// only class_name is required, the other operations fail unless overridden
+// methods take &self so that a script may pass an object to its own methods,
+// state that changes goes behind a Mutex, RwLock or atomic owned by the object
+// for-in loops iterate an object whose iter() method returns an iterator object, whose next()
+// method returns the next value, or nil when there are none left
+pub trait HostObject: Send + Sync {
+    fn class_name(&self) -> &str;
+
+    fn get(&self, name: &str) -> Result<Value, RuntimeError<'static>> {
+        Err(RuntimeError::new(format!(
+            "Undefined property '{}' on {}",
+            name,
+            self.class_name()
+        )))
+    }
+
+    fn set(&self, name: &str, _value: Value) -> Result<(), RuntimeError<'static>> {
+        Err(RuntimeError::new(format!(
+            "Can't set property '{}' on {}",
+            name,
+            self.class_name()
+        )))
+    }
+
+    fn call_method(
+        &self,
+        name: &str,
+        _arguments: &[Value],
+    ) -> Result<Value, RuntimeError<'static>> {
+        Err(RuntimeError::new(format!(
+            "Undefined method '{}' on {}",
+            name,
+            self.class_name()
+        )))
+    }
+}
+
+impl std::fmt::Debug for dyn HostObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "<{} object>", self.class_name())
+    }
+}
+
+// hashable form of the values that may be used as map keys
+// two keys are equal exactly when Expr::is_equal considers their values equal
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum MapKey {
+    Nil,
+    Boolean(bool),
+    Number(u64),
+    String(String),
+    Tuple(Vec<MapKey>),
+}
+
+impl TryFrom<&Value> for MapKey {
+    type Error = &'static str;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Nil => Ok(MapKey::Nil),
+            Value::Boolean(b) => Ok(MapKey::Boolean(*b)),
+            // NaN is never equal to itself, so a NaN key could never be looked up again
+            Value::Number(n) if n.is_nan() => Err("NaN cannot be used as a map key"),
+            // 0 == -0, so both must hash the same
+            Value::Number(n) if *n == 0.0 => Ok(MapKey::Number(0.0f64.to_bits())),
+            Value::Number(n) => Ok(MapKey::Number(n.to_bits())),
+            Value::String(s) => Ok(MapKey::String(s.clone())),
+            // tuples are immutable, so a tuple of hashable values can't change its hash
+            Value::Tuple(tuple) => Ok(MapKey::Tuple(
+                tuple
+                    .iter()
+                    .map(MapKey::try_from)
+                    .collect::<Result<_, _>>()?,
+            )),
+            Value::List(_)
+            | Value::Map(_)
+            | Value::NativeFunction(_)
+            | Value::Function(_)
+            | Value::HostObject(_) => {
+                Err("Map keys must be strings, numbers, booleans, nil or tuples of those")
+            }
+        }
+    }
+}
+
+// map that remembers insertion order
+#[derive(Debug, Clone, Default)]
+pub struct Map {
+    entries: Vec<(Value, Value)>,
+    indices: HashMap<MapKey, usize>,
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &Value) -> Result<Option<&Value>, &'static str> {
+        let key = MapKey::try_from(key)?;
+        Ok(self.indices.get(&key).map(|&i| &self.entries[i].1))
+    }
+
+    // updating an existing key keeps its original position
+    pub fn insert(&mut self, key: Value, value: Value) -> Result<(), &'static str> {
+        let map_key = MapKey::try_from(&key)?;
+        match self.indices.get(&map_key) {
+            Some(&i) => self.entries[i].1 = value,
+            None => {
+                self.indices.insert(map_key, self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Value, &Value)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
 }
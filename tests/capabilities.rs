@@ -0,0 +1,169 @@
+use rlox_twi::{Capabilities, Lox, LoxError};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// fresh directory for one test, with a data directory inside it that the script may use
+fn sandbox(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("rlox-capabilities-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("data")).unwrap();
+    fs::create_dir_all(dir.join("data2")).unwrap();
+    fs::write(dir.join("data/inside.txt"), "inside").unwrap();
+    fs::write(dir.join("data2/sibling.txt"), "sibling").unwrap();
+    fs::write(dir.join("secret.txt"), "secret").unwrap();
+    dir
+}
+
+fn runtime_error(lox: &mut Lox, source: &str) -> String {
+    match lox.eval(source) {
+        Err(LoxError::Runtime(error)) => error.msg,
+        other => panic!("unexpected {:?}", other.map(|value| value.to_string())),
+    }
+}
+
+fn read(path: &Path) -> String {
+    format!("readFile(\"{}\")", path.display())
+}
+
+#[test]
+fn new_sessions_may_only_read_the_clock() {
+    let mut lox = Lox::new();
+    assert!(lox.eval("clock()").is_ok());
+    assert_eq!(
+        runtime_error(&mut lox, "exec(\"id\", [])"),
+        "Missing capability: process spawning"
+    );
+    assert_eq!(
+        runtime_error(&mut lox, "env(\"HOME\")"),
+        "Missing capability: environment variable 'HOME'"
+    );
+    assert_eq!(
+        runtime_error(&mut lox, "readFile(\"Cargo.toml\")"),
+        "Missing capability: read access to 'Cargo.toml'"
+    );
+    assert_eq!(
+        runtime_error(&mut lox, "writeFile(\"out.txt\", \"x\")"),
+        "Missing capability: write access to 'out.txt'"
+    );
+
+    lox.set_capabilities(Capabilities::none());
+    assert_eq!(
+        runtime_error(&mut lox, "clock()"),
+        "Missing capability: clock"
+    );
+}
+
+#[test]
+fn reads_are_limited_to_the_allowed_roots() {
+    let dir = sandbox("read");
+    let mut lox = Lox::new();
+    lox.set_capabilities(Capabilities::none().allow_read(dir.join("data")));
+
+    let value = lox.eval(&read(&dir.join("data/inside.txt"))).unwrap();
+    assert_eq!(value.to_string(), "inside");
+
+    // a root matches whole path components, so data2 isn't inside data
+    let sibling = dir.join("data2/sibling.txt");
+    assert_eq!(
+        runtime_error(&mut lox, &read(&sibling)),
+        format!("Missing capability: read access to '{}'", sibling.display())
+    );
+
+    let escape = dir.join("data/../secret.txt");
+    assert_eq!(
+        runtime_error(&mut lox, &read(&escape)),
+        format!("Missing capability: read access to '{}'", escape.display())
+    );
+
+    // a path that can't be resolved is denied rather than compared as written
+    let missing = dir.join("data/missing/../../secret.txt");
+    assert!(runtime_error(&mut lox, &read(&missing)).starts_with("Missing capability"));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_out_of_a_root_are_denied() {
+    let dir = sandbox("symlink");
+    std::os::unix::fs::symlink(dir.join("secret.txt"), dir.join("data/link.txt")).unwrap();
+    std::os::unix::fs::symlink(&dir, dir.join("data/parent")).unwrap();
+    let mut lox = Lox::new();
+    lox.set_capabilities(
+        Capabilities::none()
+            .allow_read(dir.join("data"))
+            .allow_write(dir.join("data")),
+    );
+
+    assert!(runtime_error(&mut lox, &read(&dir.join("data/link.txt")))
+        .starts_with("Missing capability: read access"));
+    assert!(
+        runtime_error(&mut lox, &read(&dir.join("data/parent/secret.txt")))
+            .starts_with("Missing capability: read access")
+    );
+
+    let source = format!(
+        "writeFile(\"{}\", \"x\")",
+        dir.join("data/parent/new.txt").display()
+    );
+    assert!(runtime_error(&mut lox, &source).starts_with("Missing capability: write access"));
+    assert!(!dir.join("new.txt").exists());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn writes_may_create_files_inside_a_root() {
+    let dir = sandbox("write");
+    let mut lox = Lox::new();
+    lox.set_capabilities(Capabilities::none().allow_write(dir.join("data")));
+
+    let source = format!(
+        "writeFile(\"{}\", \"new\")",
+        dir.join("data/new.txt").display()
+    );
+    lox.eval(&source).unwrap();
+    assert_eq!(fs::read_to_string(dir.join("data/new.txt")).unwrap(), "new");
+
+    let source = format!("writeFile(\"{}\", \"x\")", dir.join("secret.txt").display());
+    assert!(runtime_error(&mut lox, &source).starts_with("Missing capability: write access"));
+    assert_eq!(
+        fs::read_to_string(dir.join("secret.txt")).unwrap(),
+        "secret"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn exec_is_killed_by_the_timeout_or_a_cancel() {
+    let mut lox = Lox::new();
+    lox.set_capabilities(Capabilities::none().allow_process());
+    assert_eq!(
+        lox.eval("exec(\"echo\", [\"hi\"])").unwrap().to_string(),
+        "hi\n"
+    );
+
+    lox.set_timeout(Some(Duration::from_millis(100)));
+    let start = Instant::now();
+    assert!(matches!(
+        lox.eval("exec(\"sleep\", [\"10\"])"),
+        Err(LoxError::Timeout(_))
+    ));
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    lox.set_timeout(None);
+    let handle = lox.cancel_handle();
+    let canceller = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        handle.cancel();
+    });
+    let start = Instant::now();
+    assert!(matches!(
+        lox.eval("exec(\"sleep\", [\"10\"])"),
+        Err(LoxError::Cancelled(_))
+    ));
+    assert!(start.elapsed() < Duration::from_secs(5));
+    canceller.join().unwrap();
+}
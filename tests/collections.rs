@@ -0,0 +1,161 @@
+use rlox_twi::Lox;
+
+fn eval(source: &str) -> String {
+    match Lox::new().eval(source) {
+        Ok(value) => value.to_string(),
+        Err(error) => panic!("{} failed: {}", source, error),
+    }
+}
+
+fn error(source: &str) -> String {
+    match Lox::new().eval(source) {
+        Ok(value) => panic!("{} evaluated to {}", source, value),
+        Err(error) => error.to_string(),
+    }
+}
+
+#[test]
+fn list_literals_and_display() {
+    assert_eq!(eval("[]"), "[]");
+    assert_eq!(
+        eval("[1, [2, \"a, b\"], [], nil]"),
+        "[1, [2, \"a, b\"], [], nil]"
+    );
+    assert_eq!(eval("[1 + 1, \"x\" + \"y\"]"), "[2, \"xy\"]");
+}
+
+#[test]
+fn list_indexing() {
+    assert_eq!(eval("[1, 2, 3][0]"), "1");
+    assert_eq!(eval("[1, 2, 3][-1]"), "3");
+    assert_eq!(eval("[[1, 2], [3, 4]][1][0]"), "3");
+    assert_eq!(eval("xs = [1, 2], xs[1] = 5, xs"), "[1, 5]");
+    assert_eq!(eval("xs = [1, 2], xs[-2] = 7, xs"), "[7, 2]");
+    // lists are shared, not copied, by assignment
+    assert_eq!(eval("xs = [1], ys = xs, ys[0] = 9, xs"), "[9]");
+
+    assert!(error("[1, 2][2]").contains("List index out of bounds\n[line: 1, column: 7"));
+    assert!(error("[1, 2][-3]").contains("List index out of bounds"));
+    assert!(error("xs = [1], xs[1] = 2").contains("List index out of bounds"));
+    assert!(error("[1][0.5]").contains("List index must be an integer"));
+    assert!(error("[1][\"0\"]").contains("List index must be an integer"));
+    assert!(error("1[0]").contains("Only lists, tuples and maps can be indexed"));
+}
+
+#[test]
+fn list_slicing() {
+    assert_eq!(eval("[1, 2, 3, 4][1:3]"), "[2, 3]");
+    assert_eq!(eval("[1, 2, 3, 4][:2]"), "[1, 2]");
+    assert_eq!(eval("[1, 2, 3, 4][2:]"), "[3, 4]");
+    assert_eq!(eval("[1, 2, 3, 4][:]"), "[1, 2, 3, 4]");
+    assert_eq!(eval("[1, 2, 3, 4][-3:-1]"), "[2, 3]");
+    // bounds are clamped and an empty range gives an empty list
+    assert_eq!(eval("[1, 2, 3, 4][-10:10]"), "[1, 2, 3, 4]");
+    assert_eq!(eval("[1, 2, 3, 4][3:1]"), "[]");
+    // a slice is a copy
+    assert_eq!(eval("xs = [1, 2], ys = xs[:], ys[0] = 9, xs"), "[1, 2]");
+
+    assert!(error("[1][0.5:]").contains("List index must be an integer"));
+    assert!(error("\"abc\"[0:1]").contains("Only lists and tuples can be sliced"));
+}
+
+#[test]
+fn lists_compare_by_value() {
+    assert_eq!(eval("[1, [2, \"a\"]] == [1, [2, \"a\"]]"), "true");
+    assert_eq!(eval("[1] == [1, 2]"), "false");
+    assert_eq!(eval("[1] != [2]"), "true");
+    assert_eq!(eval("[] == []"), "true");
+    assert_eq!(eval("[1] == (1,)"), "false");
+    assert_eq!(eval("[nil] == [false]"), "false");
+}
+
+#[test]
+fn map_literals_keep_insertion_order() {
+    assert_eq!(eval("{}"), "{}");
+    assert_eq!(eval("{\"b\": 1, \"a\": [2]}"), "{\"b\": 1, \"a\": [2]}");
+    // updating a key keeps its position, a new key goes last
+    assert_eq!(
+        eval("m = {\"b\": 1, \"a\": 2}, m[\"b\"] = 3, m[\"c\"] = 4, m"),
+        "{\"b\": 3, \"a\": 2, \"c\": 4}"
+    );
+    // a repeated key in a literal keeps the later value
+    assert_eq!(eval("{1: \"x\", 1: \"y\"}"), "{1: \"y\"}");
+}
+
+#[test]
+fn map_keys() {
+    assert_eq!(eval("{\"k\": 1}[\"k\"]"), "1");
+    assert_eq!(eval("{1: \"one\"}[1]"), "one");
+    assert_eq!(eval("{true: 1, false: 2}[false]"), "2");
+    assert_eq!(eval("{nil: 1}[nil]"), "1");
+    assert_eq!(eval("{(1, \"a\"): 2}[(1, \"a\")]"), "2");
+    // keys that compare equal are the same key
+    assert_eq!(eval("{0: \"zero\"}[-0]"), "zero");
+    assert_eq!(eval("{1: \"a\"}[2 - 1]"), "a");
+
+    assert!(error("{\"k\": 1}[\"j\"]").contains("Key not found in map"));
+    assert!(error("{[1]: 1}").contains("Map keys must be strings, numbers, booleans"));
+    assert!(error("m = {}, m[{}] = 1").contains("Map keys must be"));
+    assert!(error("{(1, [2]): 1}").contains("Map keys must be"));
+    assert!(error("{0 / 0: 1}").contains("NaN cannot be used as a map key"));
+    assert!(error("{1: 2}[0 / 0]").contains("NaN cannot be used as a map key"));
+}
+
+#[test]
+fn maps_compare_by_value_regardless_of_order() {
+    assert_eq!(eval("{\"a\": 1, \"b\": 2} == {\"b\": 2, \"a\": 1}"), "true");
+    assert_eq!(eval("{\"a\": [1]} == {\"a\": [1]}"), "true");
+    assert_eq!(eval("{\"a\": 1} == {\"a\": 2}"), "false");
+    assert_eq!(eval("{\"a\": 1} == {\"a\": 1, \"b\": 2}"), "false");
+    assert_eq!(eval("{1: nil} == {2: nil}"), "false");
+    assert_eq!(eval("len({1: 1, 2: 2})"), "2");
+}
+
+#[test]
+fn tuples() {
+    assert_eq!(eval("()"), "()");
+    assert_eq!(eval("(1,)"), "(1,)");
+    // a comma inside parentheses makes a tuple rather than a comma expression
+    assert_eq!(eval("(1, \"a\", [2])"), "(1, \"a\", [2])");
+    assert_eq!(eval("(1)"), "1");
+    assert_eq!(eval("(1, 2, 3)[-1]"), "3");
+    assert_eq!(eval("(1, 2, 3)[1:]"), "(2, 3)");
+    assert_eq!(eval("(1, (2, 3)) == (1, (2, 3))"), "true");
+    assert_eq!(eval("(1, 2) == (2, 1)"), "false");
+    assert!(error("t = (1, 2), t[0] = 3").contains("Tuples are immutable"));
+}
+
+#[test]
+fn destructuring_assignment() {
+    assert_eq!(eval("a = 1, b = 2, a, b = b, a, [a, b]"), "[2, 1]");
+    assert_eq!(eval("(a, b) = (1, 2), (a, b) = (b, a), [a, b]"), "[2, 1]");
+    // each target takes one value from the comma list, a lone value is unpacked
+    let mut lox = Lox::new();
+    lox.eval("a, b = (3, 4)").unwrap();
+    assert_eq!(lox.eval("[a, b]").unwrap().to_string(), "[3, 4]");
+    lox.eval("a, b = [5, 6]").unwrap();
+    assert_eq!(lox.eval("[a, b]").unwrap().to_string(), "[5, 6]");
+    assert_eq!(eval("a, b = (3, 4), [5], [a, b]"), "[(3, 4), [5]]");
+    assert_eq!(eval("a, (b, c) = 1, (2, 3), [a, b, c]"), "[1, 2, 3]");
+    assert_eq!(
+        eval("xs = [1, 2], xs[0], xs[1] = xs[1], xs[0], xs"),
+        "[2, 1]"
+    );
+    // the assignment yields the assigned values
+    assert_eq!(eval("a, b = 1, 2"), "(1, 2)");
+
+    assert!(error("a, b, c = 1, 2").contains(
+        "Number of values does not match the destructuring pattern\n[line: 1, column: 5"
+    ));
+    assert!(error("(a, b) = (1, 2, 3)").contains(
+        "Number of values does not match the destructuring pattern\n[line: 1, column: 1"
+    ));
+    assert!(error("a, b = 1").contains("Only tuples and lists can be unpacked"));
+    for source in ["(a, 1) = (1, 2)", "a, 1 = 2", "(a, b) += (1, 2)"] {
+        assert!(
+            error(source).contains("Invalid assignment target"),
+            "{}",
+            source
+        );
+    }
+}
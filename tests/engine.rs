@@ -0,0 +1,168 @@
+use rlox_twi::{FromLox, HostObject, IntoLox, Lox, LoxError, Program, RuntimeError, Shared, Value};
+
+use std::sync::Mutex;
+
+fn round_trip<T: IntoLox + FromLox>(value: T) -> T {
+    T::from_lox(value.into_lox()).unwrap()
+}
+
+#[test]
+fn conversions_round_trip() {
+    assert_eq!(round_trip(2.5), 2.5);
+    assert_eq!(round_trip(-7i64), -7);
+    assert!(round_trip(true));
+    assert_eq!(round_trip(String::from("lox")), "lox");
+    assert_eq!(round_trip(Some(1.0)), Some(1.0));
+    assert_eq!(round_trip(None::<f64>), None);
+    assert_eq!(
+        round_trip(vec![vec![1i64], vec![2, 3]]),
+        vec![vec![1], vec![2, 3]]
+    );
+}
+
+#[test]
+fn conversion_errors_name_the_types() {
+    let error = f64::from_lox("x".into_lox()).unwrap_err();
+    assert_eq!(error.msg, "Expected a number but got string");
+
+    let error = i64::from_lox(1.5.into_lox()).unwrap_err();
+    assert_eq!(error.msg, "Expected an integer but got number");
+
+    let two_to_63 = 9_223_372_036_854_775_808.0;
+    assert_eq!(i64::from_lox((-two_to_63).into_lox()).unwrap(), i64::MIN);
+    assert!(i64::from_lox(two_to_63.into_lox()).is_err());
+    assert!(i64::from_lox((-two_to_63 * 2.0).into_lox()).is_err());
+    assert!(i64::from_lox(f64::INFINITY.into_lox()).is_err());
+    // the largest double below 2^63
+    assert_eq!(
+        i64::from_lox(9_223_372_036_854_774_784.0.into_lox()).unwrap(),
+        9_223_372_036_854_774_784
+    );
+
+    let error = Vec::<bool>::from_lox(vec![true.into_lox(), Value::Nil].into_lox()).unwrap_err();
+    assert_eq!(error.msg, "Expected a boolean but got nil");
+}
+
+#[test]
+fn globals_pass_between_host_and_script() {
+    let mut lox = Lox::new();
+    lox.set_global("xs", vec![1.0, 2.0]);
+    lox.eval("xs[1] = xs[0] + 10").unwrap();
+    assert_eq!(lox.get::<Vec<f64>>("xs").unwrap(), vec![1.0, 11.0]);
+
+    match lox.get::<f64>("missing") {
+        Err(LoxError::Runtime(error)) => assert_eq!(error.msg, "Undefined variable 'missing'"),
+        other => panic!("unexpected {:?}", other),
+    }
+    match lox.get::<String>("xs") {
+        Err(LoxError::Runtime(error)) => assert_eq!(error.msg, "Expected a string but got list"),
+        other => panic!("unexpected {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn natives_report_errors_at_the_call() {
+    let mut lox = Lox::new();
+    lox.define_native("half", 1, |args| {
+        Ok((f64::from_lox(args[0].clone())? / 2.0).into_lox())
+    });
+    assert_eq!(lox.eval("half(3)").unwrap().to_string(), "1.5");
+
+    let error = lox.eval("half(\"a\")").unwrap_err().to_string();
+    assert!(error.contains("Expected a number but got string"));
+    assert!(error.contains("[line: 1, column: 9, operator: )]"));
+}
+
+// records the arguments of every write, formatting them the way print does
+#[derive(Default)]
+struct Log(Mutex<Vec<String>>);
+
+impl HostObject for Log {
+    fn class_name(&self) -> &str {
+        "Log"
+    }
+
+    fn get(&self, name: &str) -> Result<Value, RuntimeError<'static>> {
+        match name {
+            "count" => Ok((self.0.lock().unwrap().len() as f64).into_lox()),
+            _ => Err(RuntimeError::new(format!(
+                "Undefined property '{}' on Log",
+                name
+            ))),
+        }
+    }
+
+    fn call_method(&self, name: &str, arguments: &[Value]) -> Result<Value, RuntimeError<'static>> {
+        match name {
+            "write" => {
+                let line = arguments.iter().map(Value::to_string).collect::<Vec<_>>();
+                self.0.lock().unwrap().push(line.join(" "));
+                Ok(Value::Nil)
+            }
+            _ => Err(RuntimeError::new(format!(
+                "Undefined method '{}' on Log",
+                name
+            ))),
+        }
+    }
+}
+
+#[test]
+fn host_objects_may_be_passed_to_their_own_methods() {
+    let mut lox = Lox::new();
+    let log = Shared::new(Log::default());
+    lox.set_global("log", log.clone());
+    lox.eval("log.write(log, log.count)").unwrap();
+    lox.eval("log.write([log])").unwrap();
+    assert_eq!(
+        *log.0.lock().unwrap(),
+        vec!["<Log object> 0", "[<Log object>]"]
+    );
+
+    let error = lox.eval("log.missing()").unwrap_err().to_string();
+    assert!(error.contains("Undefined method 'missing' on Log"));
+}
+
+#[test]
+fn values_that_contain_themselves() {
+    let mut lox = Lox::new();
+    lox.set_global("xs", vec![1.0]);
+    lox.set_global("ys", vec![1.0]);
+    lox.eval("xs[0] = xs").unwrap();
+    lox.eval("ys[0] = ys").unwrap();
+
+    assert_eq!(lox.eval("xs").unwrap().to_string(), "[[...]]");
+    assert_eq!(lox.eval("xs == ys").unwrap().to_string(), "true");
+    assert_eq!(lox.eval("xs == [xs]").unwrap().to_string(), "true");
+
+    let map = lox.eval("{\"k\": [1]}").unwrap();
+    lox.set_global("m", map);
+    lox.eval("m[\"self\"] = m").unwrap();
+    assert_eq!(
+        lox.eval("m").unwrap().to_string(),
+        "{\"k\": [1], \"self\": {...}}"
+    );
+}
+
+#[test]
+fn programs_run_in_any_session() {
+    let program = Program::compile("n * 2").unwrap();
+    for n in [1.0, 2.0] {
+        let mut lox = Lox::new();
+        lox.set_global("n", n);
+        assert_eq!(
+            lox.run(&program).unwrap().to_string(),
+            (n * 2.0).to_string()
+        );
+    }
+    assert_eq!(
+        program.pretty_print(),
+        Program::compile("n * 2").unwrap().pretty_print()
+    );
+
+    assert!(matches!(Program::compile("1 +"), Err(LoxError::Parser(_))));
+    assert!(matches!(
+        Program::compile("\"open"),
+        Err(LoxError::Lexer(_))
+    ));
+}
@@ -0,0 +1,205 @@
+use rlox_twi::{Limits, Lox, LoxError, Program};
+
+fn eval(source: &str) -> String {
+    match Lox::new().eval(source) {
+        Ok(value) => value.to_string(),
+        Err(error) => panic!("{} failed: {}", source, error),
+    }
+}
+
+fn error(source: &str) -> String {
+    match Lox::new().eval(source) {
+        Ok(value) => panic!("{} evaluated to {}", source, value),
+        Err(error) => error.to_string(),
+    }
+}
+
+#[test]
+fn catch_gets_the_thrown_value() {
+    assert_eq!(
+        eval("try { throw \"oops\" } catch (e) { \"caught \" + e }"),
+        "caught oops"
+    );
+    assert_eq!(eval("try { throw [1, 2] } catch (e) { e[1] }"), "2");
+    assert_eq!(eval("try { throw nil } catch (e) { e == nil }"), "true");
+    // the try evaluates to its body's value when nothing is thrown
+    assert_eq!(eval("try { 1, 2 } catch (e) { 3 }"), "2");
+    assert_eq!(eval("try { } catch (e) { 3 }"), "nil");
+    // the catch variable is assigned like a loop variable, so it is still there afterwards
+    assert_eq!(eval("try { throw 5 } catch (e) { }, e"), "5");
+    // throw binds like an assignment
+    assert_eq!(eval("try { throw 1, 2 } catch (e) { e }"), "1");
+}
+
+#[test]
+fn throws_leave_function_calls() {
+    assert_eq!(
+        eval(
+            "check = (x) => x < 0 ? throw \"negative\" : x, \
+             twice = (x) => check(x) * 2, \
+             [try { twice(2) } catch (e) { e }, try { twice(-1) } catch (e) { e }]"
+        ),
+        "[4, \"negative\"]"
+    );
+    // the innermost try catches, a rethrow reaches the next one out
+    assert_eq!(
+        eval("try { try { throw 1 } catch (e) { throw e + 1 } } catch (e) { e * 10 }"),
+        "20"
+    );
+}
+
+#[test]
+fn runtime_errors_are_caught_as_error_objects() {
+    assert_eq!(
+        eval("try {\n  missing\n} catch (e) { e }"),
+        "{\"message\": \"Undefined variable 'missing'\", \"line\": 2, \"column\": 3}"
+    );
+    assert_eq!(
+        eval("try { 1 + \"a\" } catch (e) { e[\"message\"] }"),
+        "Invalid binary expression: Operands must be two numbers or two strings"
+    );
+    // errors of natives are located at their call
+    assert_eq!(
+        eval("try { len(1) } catch (e) { [e[\"line\"], e[\"column\"]] }"),
+        "[1, 12]"
+    );
+    assert_eq!(
+        eval("f = (a) => a, try { f() } catch (e) { e[\"message\"] }"),
+        "<fn anonymous [line: 1, column: 5]>(a) expects 1 argument(s) but got 0"
+    );
+}
+
+#[test]
+fn finally_runs_however_the_try_ends() {
+    let mut lox = Lox::new();
+    lox.eval("log = \"\"").unwrap();
+    let value = lox
+        .eval(
+            "a = try { 1 } finally { log += \"a\" }, \
+             b = try { throw 2 } catch (e) { e } finally { log += \"b\" }, \
+             for (x in [1, 2]) try { x == 1 ? continue : break } finally { log += \"${x}\" }, \
+             [a, b]",
+        )
+        .unwrap();
+    // the finally block's value is dropped
+    assert_eq!(value.to_string(), "[1, 2]");
+    assert_eq!(lox.eval("log").unwrap().to_string(), "ab12");
+
+    // without a catch the error goes on after the finally block
+    assert!(lox
+        .eval("try { throw \"kept\" } finally { log = \"\" }")
+        .unwrap_err()
+        .to_string()
+        .contains("kept"));
+    assert_eq!(lox.eval("log").unwrap().to_string(), "");
+    assert_eq!(
+        eval("try { try { throw 1 } finally { nil } } catch (e) { e }"),
+        "1"
+    );
+    // an error in the finally block replaces the one before it
+    assert_eq!(
+        eval("try { try { throw 1 } finally { throw 2 } } catch (e) { e }"),
+        "2"
+    );
+    assert_eq!(
+        eval("try { try { throw 1 } catch (e) { throw 3 } finally { nil } } catch (e) { e }"),
+        "3"
+    );
+}
+
+#[test]
+fn uncaught_throws_are_runtime_errors_at_the_throw() {
+    let result = Lox::new().eval("f = () => throw \"bad input\",\n  f()");
+    match result {
+        Err(LoxError::Runtime(error)) => assert_eq!(
+            error.to_string(),
+            "bad input\n[line: 1, column: 11, operator: throw]\n  in f, called at [line: 2, column: 5]"
+        ),
+        _ => panic!("expected a runtime error"),
+    }
+    // an error object's message, any other value as it displays
+    assert!(error("throw {\"message\": \"custom\", \"code\": 1}")
+        .starts_with("Runtime Error: custom\n"));
+    assert!(error("throw [1, \"a\"]").starts_with("Runtime Error: [1, \"a\"]\n"));
+    // a caught error thrown again keeps its message
+    assert!(error("try { missing } catch (e) { throw e }")
+        .starts_with("Runtime Error: Undefined variable 'missing'\n"));
+
+    // nothing thrown is left over for the next evaluation
+    let mut lox = Lox::new();
+    assert!(lox.eval("throw 1").is_err());
+    assert_eq!(
+        lox.eval("try { missing } catch (e) { e[\"line\"] }")
+            .unwrap()
+            .to_string(),
+        "1"
+    );
+}
+
+#[test]
+fn limits_are_not_caught_and_skip_finally() {
+    let mut lox = Lox::new();
+    lox.set_limits(Limits {
+        max_steps: Some(1000),
+        ..Limits::default()
+    });
+    lox.eval("done = false").unwrap();
+    assert!(matches!(
+        lox.eval(
+            "try { for (x in range(0, 1 / 0, 1)) nil } catch (e) { nil } finally { done = true }"
+        ),
+        Err(LoxError::StepLimit(_))
+    ));
+    assert!(!lox.get::<bool>("done").unwrap());
+
+    let mut lox = Lox::new();
+    lox.set_limits(Limits {
+        max_call_depth: Some(10),
+        ..Limits::default()
+    });
+    assert!(matches!(
+        lox.eval("f = () => try { f() } catch (e) { 0 }, f()"),
+        Err(LoxError::CallDepthLimit(_))
+    ));
+}
+
+#[test]
+fn try_syntax_errors() {
+    for (source, msg) in [
+        ("try 1 catch (e) { 2 }", "Expect '{' after 'try'"),
+        ("try { 1 catch (e) { 2 }", "Expect '}' after try block"),
+        ("try { 1 }", "Expect 'catch' or 'finally' after try block"),
+        ("try { 1 } catch e { 2 }", "Expect '(' after 'catch'"),
+        ("try { 1 } catch (1) { 2 }", "Expect catch variable name"),
+        (
+            "try { 1 } catch (e { 2 }",
+            "Expect ')' after catch variable",
+        ),
+        ("try { 1 } catch (e) 2", "Expect '{' after catch variable"),
+        ("try { 1 } catch (e) { 2", "Expect '}' after catch block"),
+        ("try { 1 } finally 2", "Expect '{' after 'finally'"),
+        ("try { 1 } finally { 2", "Expect '}' after finally block"),
+        ("throw", "Expect expression"),
+    ] {
+        match Program::compile(source) {
+            Err(LoxError::Parser(errors)) => assert_eq!(errors[0].msg, msg, "{}", source),
+            _ => panic!("{} should not parse", source),
+        }
+    }
+}
+
+#[test]
+fn try_pretty_prints_its_blocks() {
+    assert_eq!(
+        Program::compile("try { f(x) } catch (e) { throw e } finally { g() }")
+            .unwrap()
+            .pretty_print(),
+        "(try (call f x) (catch e (throw e)) (finally (call g)))"
+    );
+    assert_eq!(
+        Program::compile("try { } finally { }")
+            .unwrap()
+            .pretty_print(),
+        "(try nil (finally nil))"
+    );
+}
@@ -0,0 +1,392 @@
+use rlox_twi::{Interpreter, Limits, Lox, LoxError, Parser, Program, Scanner};
+
+fn eval(source: &str) -> String {
+    match Lox::new().eval(source) {
+        Ok(value) => value.to_string(),
+        Err(error) => panic!("{} failed: {}", source, error),
+    }
+}
+
+fn error(source: &str) -> String {
+    match Lox::new().eval(source) {
+        Ok(value) => panic!("{} evaluated to {}", source, value),
+        Err(error) => error.to_string(),
+    }
+}
+
+#[test]
+fn function_expressions_and_arrow_functions() {
+    assert_eq!(eval("add = fun (a, b) { a + b }, add(1, 2)"), "3");
+    assert_eq!(eval("double = (a) => a * 2, double(4)"), "8");
+    assert_eq!(eval("fun () { 1, 2 }()"), "2");
+    assert_eq!(eval("(() => \"now\")()"), "now");
+    // an empty body evaluates to nil
+    assert_eq!(eval("fun () { }()"), "nil");
+    // the body of an arrow function ends at a comma
+    assert_eq!(eval("f = (x) => x + 1, f(1)"), "2");
+    assert_eq!(
+        Program::compile("f = (a, b) => a + b, fun () { }")
+            .unwrap()
+            .pretty_print(),
+        "(, (= f (fun (a b) (+ a b))) (fun () nil))"
+    );
+}
+
+#[test]
+fn parentheses_are_only_an_arrow_function_before_fat_arrow() {
+    assert_eq!(eval("(1) + 2"), "3");
+    assert_eq!(eval("(a, b) = (1, 2), [a, b]"), "[1, 2]");
+    assert_eq!(
+        eval("true ? (x) => x : nil"),
+        "<fn anonymous [line: 1, column: 8]>"
+    );
+    // the '=>' ending a match guard belongs to the arm
+    assert_eq!(eval("match (1) { _ if (true) => \"guard\" }"), "guard");
+    assert_eq!(
+        eval("x = 1, match (x) { _ if ((y) => y > 0)(x) => \"positive\", _ => \"other\" }"),
+        "positive"
+    );
+    assert_eq!(eval("match (1) { _ => (x) => x }(5)"), "5");
+}
+
+#[test]
+fn functions_print_where_they_are_written() {
+    assert_eq!(eval("fun () { }"), "<fn anonymous [line: 1, column: 1]>");
+    assert_eq!(
+        eval("f = 1,\n  (a) => a"),
+        "<fn anonymous [line: 2, column: 3]>"
+    );
+    assert_eq!(eval("[() => 1]"), "[<fn anonymous [line: 1, column: 2]>]");
+    assert!(error("f = (a) => a, f()")
+        .contains("<fn anonymous [line: 1, column: 5]>(a) expects 1 argument(s) but got 0"));
+    assert!(error("(fun (a) { a })(1, 2)").contains("expects 1 argument(s) but got 2"));
+}
+
+#[test]
+fn closures_capture_the_scope_they_are_created_in() {
+    let counter = "make = fun (n) { fun () { n += 1 } }, ";
+    assert_eq!(eval(&(counter.to_owned() + "c = make(10), c(), c()")), "12");
+    // each call of make has its own n
+    assert_eq!(
+        eval(&(counter.to_owned() + "a = make(0), b = make(10), a(), a(), (a(), b())")),
+        "(3, 11)"
+    );
+    assert_eq!(
+        eval("adder = (x) => (y) => x + y, add2 = adder(2), [add2(1), adder(5)(5)]"),
+        "[3, 10]"
+    );
+    // the functions made in a loop each see the scope of the call that made them
+    assert_eq!(
+        eval("fs = [0, 0, 0], for (i in range(0, 3, 1)) fs[i] = ((i) => () => i)(i), [fs[0](), fs[2]()]"),
+        "[0, 2]"
+    );
+}
+
+#[test]
+fn variables_are_local_unless_they_already_exist_outside() {
+    // parameters shadow globals
+    assert_eq!(eval("x = 1, f = (x) => x * 10, (f(5), x)"), "(50, 1)");
+    // a global is updated, a new variable is local to the call
+    assert_eq!(eval("n = 0, inc = () => n += 1, inc(), inc(), n"), "2");
+    assert!(error("f = () => local = 1, f(), local").contains("Undefined variable 'local'"));
+    assert_eq!(
+        eval("f = fun () { total = 0, for (x in [1, 2, 3]) total += x, total }, f()"),
+        "6"
+    );
+    // a function sees globals defined after it was created
+    assert_eq!(eval("f = () => later, later = 3, f()"), "3");
+}
+
+#[test]
+fn recursion_through_a_global() {
+    assert_eq!(
+        eval("fib = (n) => n < 2 ? n : fib(n - 1) + fib(n - 2), fib(15)"),
+        "610"
+    );
+    assert_eq!(
+        eval("count = (n) => n == 0 ? 0 : 1 + count(n - 1), count(40)"),
+        "40"
+    );
+}
+
+#[test]
+fn functions_compare_by_identity() {
+    assert_eq!(
+        eval("f = () => 1, g = f, [f == g, f == (() => 1)]"),
+        "[true, false]"
+    );
+    assert!(error("f = () => 1, {f: 1}").contains("Map keys must be"));
+    assert!(error("json.stringify(() => 1)").contains("A function cannot be converted to JSON"));
+}
+
+#[test]
+fn functions_outlive_the_program_they_were_created_by() {
+    let mut lox = Lox::new();
+    {
+        let program = Program::compile("scale = 3, times = (x) => x * scale").unwrap();
+        lox.run(&program).unwrap();
+    }
+    assert_eq!(lox.eval("times(14)").unwrap().to_string(), "42");
+    assert_eq!(
+        lox.eval("twice = (f, x) => f(f(x)), twice(times, 2)")
+            .unwrap()
+            .to_string(),
+        "18"
+    );
+}
+
+#[test]
+fn functions_need_a_compiled_program() {
+    let tokens = Scanner::new("() => 1").scan_tokens().unwrap();
+    let expr = Parser::new(&tokens).parse().unwrap();
+    let error = Interpreter::new().interpret(&expr).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("Functions can only be created by a compiled Program"));
+}
+
+#[test]
+fn loops_outside_a_function_do_not_continue_into_it() {
+    match Program::compile("for (x in [1]) (() => break)") {
+        Err(LoxError::Parser(errors)) => {
+            assert_eq!(errors[0].msg, "Can't use 'break' outside of a loop")
+        }
+        _ => panic!("break inside a function body should not parse"),
+    }
+    assert_eq!(
+        eval("f = fun () { n = 0, for (x in [1, 2, 3]) x == 3 ? break : (n += x), n }, f()"),
+        "3"
+    );
+}
+
+#[test]
+fn function_syntax_errors() {
+    for (source, msg) in [
+        (
+            "fun add(a, b) { a + b }",
+            "Functions have no names, assign one instead: add = fun (...) { ... }",
+        ),
+        ("fun { }", "Expect '(' after 'fun'"),
+        ("fun (1) { }", "Expect parameter name"),
+        ("fun (a b) { }", "Expect ')' after parameters"),
+        ("fun (a, a) { }", "Duplicate parameter name"),
+        ("(a, a) => a", "Duplicate parameter name"),
+        ("fun (a) a", "Expect '{' before function body"),
+        ("fun (a) { a", "Expect '}' after function body"),
+        ("(a, 1) => a", "Expect parameter name"),
+        ("(a) =>", "Expect expression"),
+    ] {
+        match Program::compile(source) {
+            Err(LoxError::Parser(errors)) => assert_eq!(errors[0].msg, msg, "{}", source),
+            _ => panic!("{} should not parse", source),
+        }
+    }
+}
+
+#[test]
+fn deep_recursion_is_stopped_before_the_stack_overflows() {
+    // the default stack of a spawned thread, which test threads also get
+    let result = std::thread::Builder::new()
+        .stack_size(2 * 1024 * 1024)
+        .spawn(|| {
+            let mut lox = Lox::new();
+            match lox.eval("f = (n) => f(n + 1), f(0)") {
+                Err(LoxError::CallDepthLimit(error)) => error.msg,
+                other => panic!("unexpected {:?}", other.map(|value| value.to_string())),
+            }
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    assert_eq!(result, "Exceeded the stack space for nested calls");
+
+    let mut lox = Lox::new();
+    lox.set_limits(Limits {
+        max_call_depth: Some(10),
+        ..Limits::default()
+    });
+    assert!(matches!(
+        lox.eval("f = (n) => n == 0 ? 0 : f(n - 1), f(20)"),
+        Err(LoxError::CallDepthLimit(_))
+    ));
+    assert_eq!(lox.eval("f(5)").unwrap().to_string(), "0");
+}
+
+#[test]
+fn defaults_are_evaluated_at_call_time_in_the_call_scope() {
+    assert_eq!(eval("f = (a, b = 2) => a + b, [f(1), f(1, 5)]"), "[3, 6]");
+    assert_eq!(
+        eval("n = 1, f = (x = n) => x, a = f(), n = 2, [a, f()]"),
+        "[1, 2]"
+    );
+    // a default can use the parameters before it and the variables the function closes over
+    assert_eq!(eval("f = (a, b = a * 2) => b, f(4)"), "8");
+    assert_eq!(eval("make = (k) => (x = k) => x, make(7)()"), "7");
+    // only the defaults of missing arguments are evaluated
+    assert_eq!(
+        eval("calls = 0, f = (x = calls += 1) => x, f(), f(), f(10), calls"),
+        "2"
+    );
+}
+
+#[test]
+fn rest_parameters_collect_the_remaining_arguments() {
+    let rest = "f = (first, ...others) => [first, others], ";
+    assert_eq!(eval(&(rest.to_owned() + "f(1, 2, 3)")), "[1, [2, 3]]");
+    assert_eq!(eval(&(rest.to_owned() + "f(1)")), "[1, []]");
+    assert_eq!(eval("((...xs) => len(xs))()"), "0");
+    assert_eq!(eval("fun (a = 1, ...xs) { [a, xs] }(5, 6)"), "[5, [6]]");
+}
+
+#[test]
+fn named_arguments_fill_parameters_by_name() {
+    let f = "f = (a, b = 2, c = 3) => [a, b, c], ";
+    assert_eq!(eval(&(f.to_owned() + "f(1, c: 30)")), "[1, 2, 30]");
+    assert_eq!(eval(&(f.to_owned() + "f(c: 3, a: 1)")), "[1, 2, 3]");
+    assert_eq!(eval(&(f.to_owned() + "f(0, b: nil)")), "[0, nil, 3]");
+    // a name followed by a loop is still a label
+    assert_eq!(
+        Program::compile("f(outer: for (x in xs) break outer, y: 1)")
+            .unwrap()
+            .pretty_print(),
+        "(call f (outer: for x xs (break outer)) (y: 1))"
+    );
+}
+
+#[test]
+fn argument_errors_describe_the_signature() {
+    let f = "f = (a, b = 2) => a, ";
+    let g = "g = (a, ...r) => a, ";
+    for (source, msg) in [
+        (
+            f.to_owned() + "f()",
+            "<fn anonymous [line: 1, column: 5]>(a, b = 2) expects 1 to 2 argument(s) but got 0\n\
+             [line: 1, column: 24, operator: )]",
+        ),
+        (
+            f.to_owned() + "f(1, 2, 3)",
+            "<fn anonymous [line: 1, column: 5]>(a, b = 2) expects 1 to 2 argument(s) but got 3",
+        ),
+        (
+            g.to_owned() + "g()",
+            "<fn anonymous [line: 1, column: 5]>(a, ...r) expects at least 1 argument(s) but got 0",
+        ),
+        (
+            f.to_owned() + "f(1, d: 4)",
+            "<fn anonymous [line: 1, column: 5]>(a, b = 2) has no parameter named 'd'\n\
+             [line: 1, column: 27, operator: d]",
+        ),
+        (
+            g.to_owned() + "g(1, r: 2)",
+            "(a, ...r) has no parameter named 'r'",
+        ),
+        (
+            f.to_owned() + "f(1, a: 2)",
+            "(a, b = 2) got more than one value for 'a'",
+        ),
+        (
+            f.to_owned() + "f(b: 1)",
+            "(a, b = 2) is missing the argument 'a'",
+        ),
+        (
+            "len(x: [1])".to_owned(),
+            "Only functions written in Lox take named arguments, got native function\n\
+             [line: 1, column: 5, operator: x]",
+        ),
+    ] {
+        assert!(
+            error(&source).contains(msg),
+            "{}: {}",
+            source,
+            error(&source)
+        );
+    }
+}
+
+#[test]
+fn parameter_syntax_errors() {
+    for (source, msg) in [
+        (
+            "(a = 1, b) => a",
+            "Expect default value after parameters with one",
+        ),
+        ("(...a, b) => a", "Expect ')' after rest parameter"),
+        ("(...) => 1", "Expect parameter name"),
+        ("(a, ...a) => a", "Duplicate parameter name"),
+        ("f(a: 1, 2)", "Expect named argument after named arguments"),
+        ("f(a: 1, a: 2)", "Duplicate named argument"),
+        (
+            "for (x in xs) ((a = break) => a)",
+            "Can't use 'break' outside of a loop",
+        ),
+    ] {
+        match Program::compile(source) {
+            Err(LoxError::Parser(errors)) => assert_eq!(errors[0].msg, msg, "{}", source),
+            _ => panic!("{} should not parse", source),
+        }
+    }
+    assert_eq!(
+        Program::compile("(a, b = 2, ...r) => f(a, x: b)")
+            .unwrap()
+            .pretty_print(),
+        "(fun (a (= b 2) ...r) (call f a (x: b)))"
+    );
+}
+
+#[test]
+fn runtime_errors_trace_the_calls_innermost_first() {
+    assert_eq!(
+        error("inner = (x) => x + missing,\nouter = (x) => inner(x) * 2,\nouter(1)"),
+        "Runtime Error: Undefined variable 'missing'\n\
+         [line: 1, column: 20, operator: missing]\n  \
+         in inner, called at [line: 2, column: 23]\n  \
+         in outer, called at [line: 3, column: 8]"
+    );
+    match Lox::new().eval("f = () => throw \"x\", g = () => f(), g()") {
+        Err(LoxError::Runtime(error)) => {
+            let functions: Vec<_> = error
+                .trace
+                .iter()
+                .map(|frame| frame.function.as_str())
+                .collect();
+            assert_eq!(functions, ["f", "g"]);
+        }
+        _ => panic!("expected a runtime error"),
+    }
+    // natives and methods are named as they are called, other callees are anonymous
+    assert!(error("f = (s) => json.parse(s), f(\"{\")").ends_with(
+        "in json.parse, called at [line: 1, column: 24]\n  in f, called at [line: 1, column: 32]"
+    ));
+    assert!(error("[(x) => x.y][0](1)")
+        .ends_with("\n  in <anonymous>, called at [line: 1, column: 18]"));
+    // errors at the top level have no trace
+    assert!(error("missing").ends_with("operator: missing]"));
+}
+
+#[test]
+fn calls_that_never_start_are_not_traced() {
+    // a bad argument or argument list is an error of the caller
+    assert!(error("f = (a) => a, g = () => f(missing), g()")
+        .ends_with("operator: missing]\n  in g, called at [line: 1, column: 39]"));
+    assert!(error("f = (a) => a, g = () => f(), g()")
+        .ends_with("operator: )]\n  in g, called at [line: 1, column: 32]"));
+    // a caught error's trace is dropped with it
+    assert!(
+        error("f = () => missing, try { f() } catch (e) { nil }, g = () => len(1), g()").ends_with(
+            "]\n  in len, called at [line: 1, column: 66]\n  in g, called at [line: 1, column: 71]"
+        )
+    );
+}
+
+#[test]
+fn deep_traces_keep_the_innermost_and_outermost_calls() {
+    let message = error("f = (n) => n == 0 ? missing : f(n - 1),\nf(30)");
+    let lines: Vec<_> = message.lines().skip(2).collect();
+    assert_eq!(lines.len(), 13);
+    assert!(lines[..10]
+        .iter()
+        .all(|line| *line == "  in f, called at [line: 1, column: 38]"));
+    assert_eq!(lines[10], "  ... 19 more calls");
+    assert_eq!(lines[11], "  in f, called at [line: 1, column: 38]");
+    assert_eq!(lines[12], "  in f, called at [line: 2, column: 5]");
+}
@@ -0,0 +1,125 @@
+use rlox_twi::{IntoLox, Lox, LoxError, Value};
+
+fn parse(text: &str) -> Result<String, String> {
+    let mut lox = Lox::new();
+    lox.set_global("text", text);
+    match lox.eval("json.parse(text)") {
+        Ok(value) => Ok(value.to_string()),
+        Err(LoxError::Runtime(error)) => Err(error.msg),
+        Err(error) => panic!("unexpected {}", error),
+    }
+}
+
+fn stringify(value: Value, indent: &str) -> Result<String, String> {
+    let mut lox = Lox::new();
+    lox.set_global("value", value);
+    match lox.eval(&format!("json.stringify(value, {})", indent)) {
+        Ok(Value::String(json)) => Ok(json),
+        Ok(other) => panic!("unexpected {}", other),
+        Err(LoxError::Runtime(error)) => Err(error.msg),
+        Err(error) => panic!("unexpected {}", error),
+    }
+}
+
+fn nested(depth: usize) -> Value {
+    let mut value = Value::Nil;
+    for _ in 0..depth {
+        value = vec![value].into_lox();
+    }
+    value
+}
+
+#[test]
+fn strings_unescape() {
+    assert_eq!(
+        parse(r#"["a\"b\\c\/d", "\b\f\n\r\t", "é€"]"#).unwrap(),
+        "[\"a\"b\\c/d\", \"\u{8}\u{c}\n\r\t\", \"é€\"]"
+    );
+    assert_eq!(parse(r#""😀""#).unwrap(), "😀");
+
+    assert!(parse(r#""\ud83d""#)
+        .unwrap_err()
+        .ends_with("Expect low surrogate after high surrogate"));
+    assert!(parse(r#""\ud83d\u0041""#)
+        .unwrap_err()
+        .ends_with("Invalid low surrogate"));
+    assert!(parse(r#""\ude00""#)
+        .unwrap_err()
+        .ends_with("Invalid unicode escape"));
+    assert!(parse(r#""\x""#)
+        .unwrap_err()
+        .ends_with("Invalid escape sequence in JSON string"));
+    assert!(parse("\"a\nb\"")
+        .unwrap_err()
+        .ends_with("Control characters must be escaped in JSON strings"));
+}
+
+#[test]
+fn numbers_follow_the_json_grammar() {
+    assert_eq!(
+        parse("[0, -1, 1.5, 2e3, 2E-1, -0.5e+1]").unwrap(),
+        "[0, -1, 1.5, 2000, 0.2, -5]"
+    );
+
+    for text in ["01", "1.", ".5", "+1", "1e", "-", "0x10"] {
+        assert!(parse(text).is_err(), "{} should not parse", text);
+    }
+}
+
+#[test]
+fn errors_report_line_and_column() {
+    assert_eq!(
+        parse("{\n  \"a\": tru\n}").unwrap_err(),
+        "Invalid JSON at line 2, column 11: Invalid literal, expected 'true'"
+    );
+    assert_eq!(
+        parse("[1,\n2\n").unwrap_err(),
+        "Invalid JSON at line 3, column 1: Expect ',' or ']' in JSON array"
+    );
+}
+
+#[test]
+fn nesting_is_capped() {
+    assert_eq!(
+        parse(&format!("{}{}", "[".repeat(200), "]".repeat(200)))
+            .unwrap()
+            .len(),
+        400
+    );
+    assert!(parse(&"[".repeat(100_000))
+        .unwrap_err()
+        .ends_with("JSON is nested too deeply"));
+
+    assert!(stringify(nested(200), "nil").is_ok());
+    assert_eq!(
+        stringify(nested(300), "nil").unwrap_err(),
+        "Value is nested too deeply to convert to JSON"
+    );
+}
+
+#[test]
+fn stringify_escapes_and_indents() {
+    let strings = vec!["x\"\\\n\u{1}😀"].into_lox();
+    assert_eq!(stringify(strings, "nil").unwrap(), r#"["x\"\\\n\u0001😀"]"#);
+
+    let mut lox = Lox::new();
+    lox.set_global(
+        "text",
+        r#"{"a": ["x\"\u0001\ud83d\ude00", 1.5, -2e-3], "b": {"c": null}}"#,
+    );
+    let round_trip =
+        lox.eval("json.parse(json.stringify(json.parse(text), 2)) == json.parse(text)");
+    assert_eq!(round_trip.unwrap().to_string(), "true");
+
+    assert_eq!(stringify(nested(2), "1").unwrap(), "[\n [\n  null\n ]\n]");
+    // wider indents are clamped to 10 spaces instead of allocating without bound
+    let wide = format!(
+        "[\n{}[\n{}null\n{}]\n]",
+        " ".repeat(10),
+        " ".repeat(20),
+        " ".repeat(10)
+    );
+    assert_eq!(stringify(nested(2), "10").unwrap(), wide);
+    assert_eq!(stringify(nested(2), "10000000000000000000").unwrap(), wide);
+    assert!(stringify(nested(2), "-1").is_err());
+}
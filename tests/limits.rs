@@ -0,0 +1,120 @@
+use rlox_twi::{Limits, Lox, LoxError, Program};
+
+fn chain(operator: &str, terms: usize) -> String {
+    vec!["1"; terms].join(operator)
+}
+
+#[test]
+fn long_operator_chains_are_evaluated() {
+    let mut lox = Lox::new();
+    assert_eq!(
+        lox.eval(&chain("+", 200_000)).unwrap().to_string(),
+        "200000"
+    );
+    assert_eq!(lox.eval(&chain(" * ", 200_000)).unwrap().to_string(), "1");
+    assert_eq!(lox.eval(&chain(", ", 200_000)).unwrap().to_string(), "1");
+    // mixed precedences still group correctly
+    assert_eq!(
+        lox.eval(&chain(" - 2 * ", 300)).unwrap().to_string(),
+        (1 - 2 * 299).to_string()
+    );
+    assert_eq!(
+        Program::compile("1 + 2 * 3 - 4").unwrap().pretty_print(),
+        "(- (+ 1 (* 2 3)) 4)"
+    );
+
+    lox.set_limits(Limits {
+        max_steps: Some(1_000),
+        ..Limits::default()
+    });
+    assert!(matches!(
+        lox.eval(&chain("+", 200_000)),
+        Err(LoxError::StepLimit(_))
+    ));
+}
+
+#[test]
+fn long_postfix_chains_are_parse_errors() {
+    let mut lox = Lox::new();
+    for source in [
+        format!("x{}", ".y".repeat(200_000)),
+        format!("f{}", "()".repeat(200_000)),
+    ] {
+        match lox.eval(&source) {
+            Err(LoxError::Parser(errors)) => {
+                assert_eq!(errors[0].msg, "Expression is nested too deeply")
+            }
+            other => panic!("unexpected {:?}", other.map(|value| value.to_string())),
+        }
+    }
+}
+
+// the deepest nesting of each kind that still parses
+fn deepest(nest: impl Fn(usize) -> String) -> String {
+    let mut depth = 1;
+    while Program::compile(&nest(depth + 1)).is_ok() {
+        depth += 1;
+    }
+    nest(depth)
+}
+
+#[test]
+fn maximum_nesting_fits_on_a_thread_stack() {
+    let wrap = |open: &'static str, inner: &'static str, close: &'static str| {
+        move |depth: usize| format!("{}{}{}", open.repeat(depth), inner, close.repeat(depth))
+    };
+    let sources = [
+        deepest(wrap("(", "1", ")")),
+        deepest(wrap("[", "1", "]")),
+        deepest(wrap("{1: ", "1", "}")),
+        deepest(wrap("\"${", "1", "}\"")),
+        deepest(wrap("!", "true", "")),
+        deepest(wrap("true ? 1 : ", "1", "")),
+        deepest(wrap("len([", "1", "])")),
+        deepest(wrap("[0][", "0", "]")),
+        deepest(wrap("[1][0] = ", "1", "")),
+        deepest(wrap("match (1) { _ => ", "1", " }")),
+        deepest(wrap("match (1) { _ if ", "true", " => 1 }")),
+        deepest(wrap("for (x in [1]) ", "break", "")),
+        deepest(|depth| {
+            format!(
+                "match ([1]) {{ {}1{} => 1, _ => 2 }}",
+                "[".repeat(depth),
+                "]".repeat(depth)
+            )
+        }),
+    ];
+    assert!(sources[0].len() > 200);
+
+    // the default stack of a spawned thread is 2MiB
+    std::thread::spawn(move || {
+        let mut lox = Lox::new();
+        for source in &sources {
+            let program = Program::compile(source).unwrap();
+            program.pretty_print();
+            if let Err(error) = lox.run(&program) {
+                panic!("{} failed: {}", &source[..20], error);
+            }
+        }
+    })
+    .join()
+    .unwrap();
+}
+
+#[test]
+fn deeply_nested_patterns_are_parse_errors() {
+    let mut lox = Lox::new();
+    let pattern = format!("{}1{}", "[".repeat(100_000), "]".repeat(100_000));
+    let source = format!("match (1) {{ {} => 1, _ => 2 }}", pattern);
+    assert!(matches!(lox.eval(&source), Err(LoxError::Parser(_))));
+}
+
+#[test]
+fn cancel_between_evals_stops_the_next_one() {
+    let mut lox = Lox::new();
+    let handle = lox.cancel_handle();
+    handle.cancel();
+    assert!(matches!(lox.eval("1 + 2"), Err(LoxError::Cancelled(_))));
+    assert!(!handle.is_cancelled());
+    assert_eq!(lox.eval("1 + 2").unwrap().to_string(), "3");
+}
@@ -0,0 +1,282 @@
+use rlox_twi::{HostObject, Limits, Lox, LoxError, Program, RuntimeError, Shared, Value};
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+fn eval(source: &str) -> String {
+    match Lox::new().eval(source) {
+        Ok(value) => value.to_string(),
+        Err(error) => panic!("{} failed: {}", source, error),
+    }
+}
+
+fn error(source: &str) -> String {
+    match Lox::new().eval(source) {
+        Ok(value) => panic!("{} evaluated to {}", source, value),
+        Err(error) => error.to_string(),
+    }
+}
+
+#[test]
+fn for_in_walks_lists_tuples_maps_and_strings() {
+    assert_eq!(eval("t = 0, for (x in [1, 2, 3]) t += x, t"), "6");
+    assert_eq!(eval("t = 0, for (x in (4, 5)) t += x, t"), "9");
+    // maps give their keys in insertion order
+    assert_eq!(
+        eval("s = \"\", m = {\"b\": 1, \"a\": 2}, for (k in m) s += k + \"${m[k]}\", s"),
+        "b1a2"
+    );
+    assert_eq!(eval("s = \"\", for (c in \"héllo\") s = c + s, s"), "olléh");
+    assert_eq!(eval("n = 0, for (x in []) n++, n"), "0");
+    // the loop is an expression that yields nil, the variable keeps the last value
+    assert_eq!(eval("for (x in [1, 2]) x"), "nil");
+    assert_eq!(eval("for (x in [1, 2]) nil, x"), "2");
+    assert_eq!(
+        Program::compile("for (x in xs) t += x")
+            .unwrap()
+            .pretty_print(),
+        "(for x xs (+= t x))"
+    );
+}
+
+#[test]
+fn for_in_walks_a_snapshot_of_the_container() {
+    assert_eq!(
+        eval("xs = [1, 2], n = 0, for (x in xs) (xs[0] = 9, n += x), [n, xs]"),
+        "[3, [9, 2]]"
+    );
+    assert_eq!(eval("m = {1: 1}, for (k in m) m[k + 1] = 1, len(m)"), "2");
+}
+
+#[test]
+fn range_counts_by_its_step() {
+    let collect = |range: &str| {
+        eval(&format!(
+            "s = \"\", for (x in {}) s += \"${{x}} \", s",
+            range
+        ))
+    };
+    assert_eq!(collect("range(0, 5, 1)"), "0 1 2 3 4 ");
+    assert_eq!(collect("range(10, 0, -3)"), "10 7 4 1 ");
+    assert_eq!(collect("range(0, 1, 0.25)"), "0 0.25 0.5 0.75 ");
+    assert_eq!(collect("range(3, 3, 1)"), "");
+    assert_eq!(collect("range(3, 0, 1)"), "");
+    assert_eq!(
+        eval("r = range(1, 2, 3), [r.start, r.end, r.step]"),
+        "[1, 2, 3]"
+    );
+    assert_eq!(eval("range(0, 1, 1)"), "<range object>");
+
+    assert!(error("range(0, 1, 0)").contains("range() step must not be zero"));
+    assert!(error("range(0, \"1\", 1)").contains("range() expects three numbers"));
+    assert!(error("range(0, 1)").contains("range() expects 3 argument(s) but got 2"));
+}
+
+#[test]
+fn huge_ranges_are_walked_lazily_and_stopped_by_the_limits() {
+    let mut lox = Lox::new();
+    lox.set_limits(Limits {
+        max_steps: Some(10_000),
+        ..Limits::default()
+    });
+    assert!(matches!(
+        lox.eval("for (x in range(0, 1 / 0, 1)) nil"),
+        Err(LoxError::StepLimit(_))
+    ));
+}
+
+#[test]
+fn non_iterables_are_reported_at_in() {
+    assert!(error("for (x in 1) nil").ends_with(
+        "Only lists, tuples, maps, strings and iterable objects can be iterated, got number\n[line: 1, column: 8, operator: in]"
+    ));
+    assert!(error("for (x in json) nil").contains("Undefined method 'iter' on json"));
+    // the iterable is evaluated before the loop starts
+    assert!(error("for (x in missing) nil").contains("Undefined variable 'missing'"));
+}
+
+#[test]
+fn for_in_syntax_errors() {
+    for (source, msg) in [
+        ("for x in xs) nil", "Expect '(' after 'for'"),
+        ("for (1 in xs) nil", "Expect loop variable name"),
+        ("for (x xs) nil", "Expect 'in' after loop variable"),
+        ("for (x in xs nil", "Expect ')' after loop iterable"),
+        ("for (x in xs)", "Expect expression"),
+    ] {
+        match Program::compile(source) {
+            Err(LoxError::Parser(errors)) => assert_eq!(errors[0].msg, msg, "{}", source),
+            _ => panic!("{} should not parse", source),
+        }
+    }
+}
+
+// counts down from its start, one iterator at a time
+struct Countdown(usize);
+
+struct CountdownIterator(AtomicUsize);
+
+impl HostObject for Countdown {
+    fn class_name(&self) -> &str {
+        "Countdown"
+    }
+
+    fn call_method(
+        &self,
+        name: &str,
+        _arguments: &[Value],
+    ) -> Result<Value, RuntimeError<'static>> {
+        match name {
+            "iter" => Ok(Value::HostObject(Shared::new(CountdownIterator(
+                AtomicUsize::new(self.0),
+            )))),
+            _ => Err(RuntimeError::new("Undefined method")),
+        }
+    }
+}
+
+impl HostObject for CountdownIterator {
+    fn class_name(&self) -> &str {
+        "CountdownIterator"
+    }
+
+    fn call_method(
+        &self,
+        _name: &str,
+        _arguments: &[Value],
+    ) -> Result<Value, RuntimeError<'static>> {
+        match self.0.load(Ordering::Relaxed) {
+            0 => Ok(Value::Nil),
+            n => {
+                self.0.store(n - 1, Ordering::Relaxed);
+                Ok(Value::Number(n as f64))
+            }
+        }
+    }
+}
+
+#[test]
+fn host_objects_follow_the_iterator_protocol() {
+    let mut lox = Lox::new();
+    lox.set_global("countdown", Value::HostObject(Shared::new(Countdown(3))));
+    assert_eq!(
+        lox.eval("s = \"\", for (n in countdown) s += \"${n}\", s")
+            .unwrap()
+            .to_string(),
+        "321"
+    );
+    // each loop gets a fresh iterator
+    assert_eq!(
+        lox.eval("t = 0, for (n in countdown) t += n, t")
+            .unwrap()
+            .to_string(),
+        "6"
+    );
+    lox.set_global(
+        "iterator",
+        Value::HostObject(Shared::new(CountdownIterator(AtomicUsize::new(1)))),
+    );
+    assert!(lox
+        .eval("for (n in iterator) nil")
+        .unwrap_err()
+        .to_string()
+        .contains("iter() on CountdownIterator must return an iterator object, got number"));
+}
+
+#[test]
+fn break_and_continue_apply_to_the_innermost_loop() {
+    assert_eq!(
+        eval("s = \"\", for (x in range(0, 10, 1)) x == 5 ? break : (s += \"${x}\"), s"),
+        "01234"
+    );
+    assert_eq!(
+        eval("s = \"\", for (x in [1, 2, 3, 4]) (x == 2 ? continue : nil, s += \"${x}\"), s"),
+        "134"
+    );
+    assert_eq!(
+        eval(
+            "n = 0, for (i in range(0, 3, 1)) (for (j in range(0, 3, 1)) j == 1 ? break : n++), n"
+        ),
+        "3"
+    );
+    // a jump leaves every expression between it and its loop
+    assert_eq!(eval("xs = [], for (x in [1]) xs = [0, break], xs"), "[]");
+    assert_eq!(
+        eval("n = 0, for (x in [1, 2]) (len([continue]), n++), n"),
+        "0"
+    );
+    assert_eq!(
+        Program::compile("for (x in xs) x ? break : continue")
+            .unwrap()
+            .pretty_print(),
+        "(for x xs (?: x (break) (continue)))"
+    );
+}
+
+#[test]
+fn labels_name_the_loop_to_leave() {
+    assert_eq!(
+        eval(
+            "s = \"\", outer: for (i in range(0, 3, 1)) for (j in range(0, 3, 1)) \
+             j > i ? continue outer : i == 2 ? break outer : (s += \"${i}${j} \"), s"
+        ),
+        "00 10 11 "
+    );
+    assert_eq!(
+        Program::compile("outer: for (i in xs) for (j in ys) break outer")
+            .unwrap()
+            .pretty_print(),
+        "(outer: for i xs (for j ys (break outer)))"
+    );
+    // a label is only a label in front of a loop
+    assert_eq!(eval("outer = 1, true ? outer : 2"), "1");
+}
+
+#[test]
+fn jumps_outside_of_loops_are_parse_errors() {
+    for (source, msg) in [
+        (
+            "break",
+            "Line: 1, Column: 1, Token: break, Cause: Can't use 'break' outside of a loop",
+        ),
+        (
+            "1, continue",
+            "Line: 1, Column: 4, Token: continue, Cause: Can't use 'continue' outside of a loop",
+        ),
+        // the iterable isn't part of the loop
+        (
+            "for (x in [break]) nil",
+            "Line: 1, Column: 12, Token: break, Cause: Can't use 'break' outside of a loop",
+        ),
+        (
+            "a: for (x in xs) break b",
+            "Line: 1, Column: 24, Token: b, Cause: No enclosing loop has this label",
+        ),
+        (
+            "(a: for (x in xs) nil, for (y in ys) continue a)",
+            "Line: 1, Column: 47, Token: a, Cause: No enclosing loop has this label",
+        ),
+        (
+            "a: for (x in xs) a: for (y in ys) nil",
+            "Line: 1, Column: 18, Token: a, Cause: An enclosing loop already has this label",
+        ),
+    ] {
+        match Program::compile(source) {
+            Err(LoxError::Parser(errors)) => assert_eq!(errors[0].to_string(), msg),
+            _ => panic!("{} should not parse", source),
+        }
+    }
+}
+
+#[test]
+fn errors_and_limits_are_not_stopped_by_loops() {
+    assert!(error("for (x in [1, 2]) missing").contains("Undefined variable 'missing'"));
+    let mut lox = Lox::new();
+    lox.set_limits(Limits {
+        max_steps: Some(100),
+        ..Limits::default()
+    });
+    assert!(matches!(
+        lox.eval("for (x in range(0, 1000, 1)) continue"),
+        Err(LoxError::StepLimit(_))
+    ));
+}
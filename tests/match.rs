@@ -0,0 +1,180 @@
+use rlox_twi::{Lox, LoxError, Program};
+
+fn eval(source: &str) -> String {
+    match Lox::new().eval(source) {
+        Ok(value) => value.to_string(),
+        Err(error) => panic!("{} failed: {}", source, error),
+    }
+}
+
+fn error(source: &str) -> String {
+    match Lox::new().eval(source) {
+        Ok(value) => panic!("{} evaluated to {}", source, value),
+        Err(error) => error.to_string(),
+    }
+}
+
+// the body of the first arm matching the scrutinee, given as source
+fn arm(scrutinee: &str, arms: &str) -> String {
+    eval(&format!("match ({}) {{ {} }}", scrutinee, arms))
+}
+
+#[test]
+fn literal_patterns_compare_by_value() {
+    let arms = "1 => \"one\", -2 => \"minus two\", \"1\" => \"string\", true => \"true\", \
+                nil => \"nil\", _ => \"other\"";
+    assert_eq!(arm("1", arms), "one");
+    assert_eq!(arm("3 - 5", arms), "minus two");
+    assert_eq!(arm("\"1\"", arms), "string");
+    assert_eq!(arm("1 == 1", arms), "true");
+    assert_eq!(arm("nil", arms), "nil");
+    assert_eq!(arm("false", arms), "other");
+    assert_eq!(arm("[1]", arms), "other");
+    assert_eq!(arm("-0", "0 => \"zero\""), "zero");
+}
+
+#[test]
+fn range_patterns_include_the_start_and_exclude_the_end() {
+    let arms =
+        "-10..0 => \"negative\", 0..10 => \"digit\", 10..100 => \"two digits\", _ => \"other\"";
+    assert_eq!(arm("-10", arms), "negative");
+    assert_eq!(arm("0", arms), "digit");
+    assert_eq!(arm("9.5", arms), "digit");
+    assert_eq!(arm("10", arms), "two digits");
+    assert_eq!(arm("100", arms), "other");
+    assert_eq!(arm("\"5\"", arms), "other");
+    assert_eq!(arm("0 / 0", arms), "other");
+}
+
+#[test]
+fn list_and_tuple_patterns_match_element_by_element() {
+    let arms = "[] => \"empty\", [1] => \"one\", [1, _] => \"one and any\", \
+                [[_, 2], 0..5] => \"nested\", _ => \"other\"";
+    assert_eq!(arm("[]", arms), "empty");
+    assert_eq!(arm("[1]", arms), "one");
+    assert_eq!(arm("[1, \"x\"]", arms), "one and any");
+    assert_eq!(arm("[[0, 2], 4]", arms), "nested");
+    assert_eq!(arm("[1, 2, 3]", arms), "other");
+    // a list pattern doesn't match a tuple and the other way around
+    assert_eq!(arm("(1,)", arms), "other");
+    assert_eq!(arm("[1, 2]", "(1, 2) => \"tuple\", _ => \"list\""), "list");
+
+    // a comma in the scrutinee makes a tuple
+    let arms = "(0, 0) => \"origin\", (0, _) => \"y axis\", (_, 0) => \"x axis\", () => \"unit\", \
+                (_,) => \"single\", _ => \"other\"";
+    assert_eq!(arm("0, 0", arms), "origin");
+    assert_eq!(arm("0, 3", arms), "y axis");
+    assert_eq!(arm("3, 0", arms), "x axis");
+    assert_eq!(arm("", arms), "unit");
+    assert_eq!(arm("3,", arms), "single");
+    assert_eq!(arm("1, 2, 3", arms), "other");
+}
+
+#[test]
+fn map_patterns_need_the_listed_keys_only() {
+    let arms = "{\"kind\": \"circle\", \"r\": 0..1} => \"small circle\", \
+                {\"kind\": \"circle\"} => \"circle\", {} => \"map\", _ => \"other\"";
+    assert_eq!(
+        arm("{\"kind\": \"circle\", \"r\": 0.5}", arms),
+        "small circle"
+    );
+    assert_eq!(
+        arm("{\"r\": 5, \"kind\": \"circle\", \"x\": 1}", arms),
+        "circle"
+    );
+    assert_eq!(arm("{\"kind\": \"square\"}", arms), "map");
+    assert_eq!(arm("[\"kind\"]", arms), "other");
+    assert_eq!(
+        arm("{1: [3], nil: 2}", "{1: [_], nil: 2} => \"keys\""),
+        "keys"
+    );
+}
+
+#[test]
+fn wildcard_matches_anything_including_nil() {
+    for scrutinee in ["nil", "1", "[]", "{}", "len", "json"] {
+        assert_eq!(arm(scrutinee, "_ => \"any\""), "any");
+    }
+    assert_eq!(arm("[1, 2]", "[_, _] => 2, [_] => 1"), "2");
+}
+
+#[test]
+fn guards_are_evaluated_only_for_matching_patterns() {
+    let mut lox = Lox::new();
+    lox.eval("calls = 0").unwrap();
+    let source = "match (5) { \
+                  0..3 if (calls += 1) > 0 => \"low\", \
+                  0..10 if (calls += 1) > 9 => \"never\", \
+                  0..10 if (calls += 1) > 0 => \"mid\", \
+                  _ => \"other\" }";
+    assert_eq!(lox.eval(source).unwrap().to_string(), "mid");
+    // the first guard was skipped because its pattern didn't match
+    assert_eq!(lox.get::<f64>("calls").unwrap(), 2.0);
+    assert_eq!(
+        arm("1", "_ if nil => \"no\", _ if 0 => \"zero is truthy\""),
+        "zero is truthy"
+    );
+}
+
+#[test]
+fn only_the_chosen_body_is_evaluated() {
+    let mut lox = Lox::new();
+    let value = lox
+        .eval("hits = [], match (2) { 1 => hits = [1], 2 => hits = [2], _ => hits = [0] }, hits")
+        .unwrap();
+    assert_eq!(value.to_string(), "[2]");
+    assert_eq!(
+        eval("s = \"\", for (x in [1, 2, 3]) match (x) { 2 => continue, _ => s += \"${x}\" }, s"),
+        "13"
+    );
+}
+
+#[test]
+fn non_exhaustive_match_is_reported_at_the_keyword() {
+    assert_eq!(
+        error("x = 4,\n  match (x) { 1 => 1, 2..4 => 2 }"),
+        "Runtime Error: Non-exhaustive match: no arm matches the value\n\
+         [line: 2, column: 3, operator: match]"
+    );
+    assert!(error("match (1) { }").contains("Non-exhaustive match"));
+    assert!(error("match (1) { _ if false => 1 }").contains("Non-exhaustive match"));
+}
+
+#[test]
+fn pattern_syntax_errors() {
+    for (source, msg) in [
+        ("match 1 { _ => 1 }", "Expect '(' after 'match'"),
+        ("match (1) _ => 1", "Expect '{' before match arms"),
+        ("match (1) { x => 1 }", "Expect pattern"),
+        (
+            "match (1) { 1 + 1 => 1 }",
+            "Expect '=>' after match pattern",
+        ),
+        (
+            "match (1) { -\"a\" => 1 }",
+            "Expect number after '-' in pattern",
+        ),
+        (
+            "match (1) { \"a\"..\"z\" => 1 }",
+            "Range pattern bounds must be numbers",
+        ),
+        ("match (1) { [1 => 1 }", "Expect ']' after list pattern"),
+        ("match (1) { {1 => 1} }", "Expect ':' after map pattern key"),
+        ("match (1) { _ => 1 _ => 2 }", "Expect '}' after match arms"),
+    ] {
+        match Program::compile(source) {
+            Err(LoxError::Parser(errors)) => assert_eq!(errors[0].msg, msg, "{}", source),
+            _ => panic!("{} should not parse", source),
+        }
+    }
+}
+
+#[test]
+fn match_pretty_prints_its_arms() {
+    assert_eq!(
+        Program::compile("match (x) { [1, _] => a, 1..2 if b => c, {\"k\": (nil,)} => d, _ => e }")
+            .unwrap()
+            .pretty_print(),
+        "(match (x) ((list 1 _) => a) (1..2 if b => c) ((map (k (tuple nil))) => d) (_ => e))"
+    );
+}
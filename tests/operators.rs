@@ -0,0 +1,232 @@
+use rlox_twi::{FromLox, HostObject, IntoLox, Lox, LoxError, Program, RuntimeError, Shared, Value};
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+fn eval(source: &str) -> String {
+    match Lox::new().eval(source) {
+        Ok(value) => value.to_string(),
+        Err(error) => panic!("{} failed: {}", source, error),
+    }
+}
+
+fn error(source: &str) -> String {
+    match Lox::new().eval(source) {
+        Ok(value) => panic!("{} evaluated to {}", source, value),
+        Err(error) => error.to_string(),
+    }
+}
+
+fn prefix(source: &str) -> String {
+    Program::compile(source).unwrap().pretty_print()
+}
+
+#[test]
+fn ternary_selects_a_branch() {
+    assert_eq!(eval("true ? 1 : 2"), "1");
+    assert_eq!(eval("nil ? 1 : 2"), "2");
+    assert_eq!(eval("0 ? 1 : 2"), "1");
+    // only the selected branch is evaluated
+    assert_eq!(eval("true ? 1 : missing"), "1");
+    assert_eq!(eval("false ? missing : 2"), "2");
+    assert!(error("true ? missing : 2").contains("Undefined variable 'missing'"));
+}
+
+#[test]
+fn ternary_is_right_associative_and_binds_below_equality() {
+    assert_eq!(eval("false ? 1 : true ? 2 : 3"), "2");
+    assert_eq!(prefix("a ? b : c ? d : e"), "(?: a b (?: c d e))");
+    assert_eq!(eval("1 == 2 ? \"a\" : \"b\""), "b");
+    assert_eq!(prefix("a == b ? c : d"), "(?: (== a b) c d)");
+    // the then branch may hold a comma expression, the else branch binds tighter than a comma
+    assert_eq!(eval("true ? 1, 2 : 3"), "2");
+    assert_eq!(prefix("a ? b : c, d"), "(, (?: a b c) d)");
+}
+
+#[test]
+fn ternary_without_colon_is_a_parse_error() {
+    match Lox::new().eval("true ? 1 2") {
+        Err(LoxError::Parser(errors)) => {
+            assert_eq!(
+                errors[0].msg,
+                "Expect ':' after then branch of conditional expression"
+            );
+            assert_eq!(
+                errors[0].to_string(),
+                "Line: 1, Column: 10, Token: 2, Cause: Expect ':' after then branch of conditional expression"
+            );
+        }
+        other => panic!("unexpected {:?}", other.map(|value| value.to_string())),
+    }
+    assert!(error("true ? 1").contains("Token: eof"));
+}
+
+#[test]
+fn comma_evaluates_left_to_right_and_yields_the_last_value() {
+    assert_eq!(eval("1, 2, 3"), "3");
+    assert_eq!(prefix("a, b, c"), "(, (, a b) c)");
+
+    let mut lox = Lox::new();
+    lox.set_global("xs", vec![0.0]);
+    assert_eq!(lox.eval("xs[0] = 5, xs[0] + 1").unwrap().to_string(), "6");
+    // an error in any operand ends the whole expression
+    assert!(lox.eval("xs[0] = 7, missing, xs[0] = 9").is_err());
+    assert_eq!(lox.get::<Vec<f64>>("xs").unwrap(), vec![7.0]);
+}
+
+fn parse_errors(source: &str) -> Vec<String> {
+    match Program::compile(source) {
+        Err(LoxError::Parser(errors)) => errors.iter().map(|error| error.to_string()).collect(),
+        Err(other) => panic!("unexpected {}", other),
+        Ok(program) => panic!("{} parsed as {}", source, program.pretty_print()),
+    }
+}
+
+#[test]
+fn binary_operator_without_left_operand_is_reported_at_the_operator() {
+    assert_eq!(
+        parse_errors("* 5"),
+        ["Line: 1, Column: 1, Token: *, Cause: binary operator '*' is missing its left operand"]
+    );
+    assert_eq!(
+        parse_errors("(== 3)"),
+        ["Line: 1, Column: 2, Token: ==, Cause: binary operator '==' is missing its left operand"]
+    );
+    for operator in ["+", "/", "!=", "<", "<=", ">", ">="] {
+        let errors = parse_errors(&format!("1, {} 2", operator));
+        assert!(errors[0].ends_with(&format!(
+            "binary operator '{}' is missing its left operand",
+            operator
+        )));
+    }
+    // the right operand is consumed, so its own errors don't hide the missing operand
+    assert!(parse_errors("* (1 +")[0].contains("missing its left operand"));
+    // a leading minus is negation
+    assert_eq!(eval("- 5"), "-5");
+}
+
+#[test]
+fn assignment_operators_on_variables() {
+    let mut lox = Lox::new();
+    assert_eq!(lox.eval("x = 2").unwrap().to_string(), "2");
+    assert_eq!(lox.eval("x += 3").unwrap().to_string(), "5");
+    assert_eq!(lox.eval("x -= 1").unwrap().to_string(), "4");
+    assert_eq!(lox.eval("x *= 3").unwrap().to_string(), "12");
+    assert_eq!(lox.eval("x /= 8").unwrap().to_string(), "1.5");
+    // prefix operators yield the new value, postfix ones the old value
+    assert_eq!(lox.eval("x = 1, ++x").unwrap().to_string(), "2");
+    assert_eq!(lox.eval("x++").unwrap().to_string(), "2");
+    assert_eq!(lox.eval("--x").unwrap().to_string(), "2");
+    assert_eq!(lox.eval("x--").unwrap().to_string(), "2");
+    assert_eq!(lox.get::<f64>("x").unwrap(), 1.0);
+    assert_eq!(lox.eval("s = \"a\", s += \"b\"").unwrap().to_string(), "ab");
+    // = is right-associative and defines the global, the other operators need an existing one
+    assert_eq!(lox.eval("a = b = 3, a + b").unwrap().to_string(), "6");
+    for source in ["y += 1", "y++", "--y"] {
+        let error = lox.eval(source).unwrap_err().to_string();
+        assert!(error.contains("Undefined variable 'y'"), "{}", error);
+    }
+}
+
+#[test]
+fn assignment_operators_on_indexed_elements() {
+    let mut lox = Lox::new();
+    lox.set_global("xs", vec![1.0, 2.0]);
+    assert_eq!(lox.eval("xs[0] += 10").unwrap().to_string(), "11");
+    assert_eq!(lox.eval("xs[-1]++").unwrap().to_string(), "2");
+    assert_eq!(lox.eval("--xs[1]").unwrap().to_string(), "2");
+    assert_eq!(lox.get::<Vec<f64>>("xs").unwrap(), vec![11.0, 2.0]);
+    assert_eq!(
+        lox.eval("m = {\"k\": 1}, m[\"k\"] *= 5, m")
+            .unwrap()
+            .to_string(),
+        "{\"k\": 5}"
+    );
+    assert!(lox
+        .eval("(1, 2)[0] += 1")
+        .unwrap_err()
+        .to_string()
+        .contains("Tuples are immutable"));
+}
+
+#[test]
+fn assignment_target_is_evaluated_once() {
+    let mut lox = Lox::new();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&calls);
+    lox.define_native("next", 0, move |_| {
+        Ok((counter.fetch_add(1, Ordering::Relaxed) as i64).into_lox())
+    });
+    lox.set_global("xs", vec![1.0, 2.0]);
+    lox.eval("xs[next()] += 5").unwrap();
+    lox.eval("xs[next()]++").unwrap();
+    assert_eq!(calls.load(Ordering::Relaxed), 2);
+    assert_eq!(lox.get::<Vec<f64>>("xs").unwrap(), vec![6.0, 3.0]);
+}
+
+// object with a single numeric field
+struct Counter(Mutex<f64>);
+
+impl HostObject for Counter {
+    fn class_name(&self) -> &str {
+        "Counter"
+    }
+
+    fn get(&self, _name: &str) -> Result<Value, RuntimeError<'static>> {
+        Ok(self.0.lock().unwrap().into_lox())
+    }
+
+    fn set(&self, _name: &str, value: Value) -> Result<(), RuntimeError<'static>> {
+        *self.0.lock().unwrap() = f64::from_lox(value)?;
+        Ok(())
+    }
+}
+
+#[test]
+fn assignment_operators_on_fields() {
+    let mut lox = Lox::new();
+    let counter = Shared::new(Counter(Mutex::new(1.0)));
+    lox.set_global("c", counter.clone());
+    assert_eq!(lox.eval("c.n += 2").unwrap().to_string(), "3");
+    assert_eq!(lox.eval("c.n++").unwrap().to_string(), "3");
+    assert_eq!(lox.eval("--c.n").unwrap().to_string(), "3");
+    assert_eq!(*counter.0.lock().unwrap(), 3.0);
+}
+
+#[test]
+fn only_places_can_be_assigned_to() {
+    for source in ["1 = 2", "x + 1 += 2", "f() = 1", "++1", "(a, b) += 1"] {
+        let errors = parse_errors(source);
+        assert!(
+            errors[0].ends_with("Invalid assignment target"),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn precedence_and_associativity() {
+    let cases = [
+        ("1 + 2 * 3", "(+ 1 (* 2 3))", "7"),
+        ("(1 + 2) * 3", "(* ((+ 1 2)) 3)", "9"),
+        ("10 - 4 - 3", "(- (- 10 4) 3)", "3"),
+        ("8 / 4 / 2", "(/ (/ 8 4) 2)", "1"),
+        ("-2 * 3", "(* (-2) 3)", "-6"),
+        ("- -2", "(-(-2))", "2"),
+        ("!true == false", "(== (!true) false)", "true"),
+        ("1 < 2 == 2 < 3", "(== (< 1 2) (< 2 3))", "true"),
+        ("1 + 2 < 2 * 2", "(< (+ 1 2) (* 2 2))", "true"),
+        ("-[1, 2][0]", "(-([] (list 1 2) 0))", "-1"),
+        (
+            "x = 1 + 2 == 3 ? 4 : 5",
+            "(= x (?: (== (+ 1 2) 3) 4 5))",
+            "4",
+        ),
+        ("x = 1, x++ + 1", "(, (= x 1) (+ (x++) 1))", "2"),
+    ];
+    for (source, tree, value) in cases {
+        assert_eq!(prefix(source), tree, "{}", source);
+        assert_eq!(eval(source), value, "{}", source);
+    }
+}
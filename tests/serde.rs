@@ -0,0 +1,44 @@
+#![cfg(feature = "serde")]
+
+use rlox_twi::{Lox, Value};
+
+#[test]
+fn config_round_trips_through_json() {
+    let config: Value = serde_json::from_str(
+        r#"{"name": "lox", "retries": 3, "ratio": 0.5, "tags": ["a", "b"], "extra": null}"#,
+    )
+    .unwrap();
+
+    let mut lox = Lox::new();
+    lox.set_global("config", config);
+    assert_eq!(lox.eval(r#"config["tags"][1]"#).unwrap().to_string(), "b");
+
+    let result = lox
+        .eval(r#"{"retries": config["retries"] + 1, "ok": true, "pair": (1, "x")}"#)
+        .unwrap();
+    assert_eq!(
+        serde_json::to_string(&result).unwrap(),
+        r#"{"retries":4,"ok":true,"pair":[1,"x"]}"#
+    );
+}
+
+#[test]
+fn functions_cannot_be_serialized() {
+    let mut lox = Lox::new();
+    let value = lox.eval("[1, clock]").unwrap();
+    let error = serde_json::to_string(&value).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("native function 'clock' cannot be serialized"));
+}
+
+#[test]
+fn values_that_contain_themselves_cannot_be_serialized() {
+    let mut lox = Lox::new();
+    lox.set_global("xs", vec![1.0]);
+    let value = lox.eval("xs[0] = xs, xs").unwrap();
+    let error = serde_json::to_string(&value).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("a list that contains itself cannot be serialized"));
+}
@@ -0,0 +1,56 @@
+use rlox_twi::{Lox, LoxError, Program};
+
+fn eval(source: &str) -> String {
+    match Lox::new().eval(source) {
+        Ok(value) => value.to_string(),
+        Err(error) => panic!("{} failed: {}", source, error),
+    }
+}
+
+fn error(source: &str) -> String {
+    match Lox::new().eval(source) {
+        Ok(value) => panic!("{} evaluated to {}", source, value),
+        Err(error) => error.to_string(),
+    }
+}
+
+#[test]
+fn interpolation_stringifies_each_expression() {
+    assert_eq!(eval("\"total: ${1 + 2}\""), "total: 3");
+    assert_eq!(eval("\"${1}${2}\""), "12");
+    assert_eq!(eval("\"${\"a\"}\""), "a");
+    assert_eq!(
+        eval("\"${[1, \"a\"]} ${nil} ${true} ${(1,)} ${{\"k\": 2}}\""),
+        "[1, \"a\"] nil true (1,) {\"k\": 2}"
+    );
+    // braces inside the expression don't end it
+    assert_eq!(eval("\"x${ {1: 2}[1] }y\""), "x2y");
+    assert_eq!(eval("\"a${\"b${1}c\"}d\""), "ab1cd");
+    assert_eq!(
+        Program::compile("\"a${x}b${y}c\"").unwrap().pretty_print(),
+        "(interpolate a x b y c)"
+    );
+}
+
+#[test]
+fn errors_inside_interpolation_point_at_their_column() {
+    assert!(error("x = 1, \"ab ${x + nil}\"").ends_with("[line: 1, column: 16, operator: +]"));
+    assert!(error("\"a\n${1 + nil}\"").ends_with("[line: 2, column: 5, operator: +]"));
+    assert!(error("\"${missing}\"").ends_with("[line: 1, column: 4, operator: missing]"));
+
+    match Lox::new().eval("\"${1 +}\"") {
+        Err(LoxError::Parser(errors)) => assert_eq!(
+            errors[0].to_string(),
+            "Line: 1, Column: 7, Token: }\", Cause: Expect expression"
+        ),
+        other => panic!("unexpected {:?}", other.map(|value| value.to_string())),
+    }
+}
+
+#[test]
+fn unterminated_interpolation_is_a_lexical_error() {
+    for source in ["\"${1", "\"${1}"] {
+        assert!(matches!(Lox::new().eval(source), Err(LoxError::Lexer(_))));
+    }
+    assert!(error("\"${1").contains("Unterminated string interpolation"));
+}
@@ -0,0 +1,51 @@
+#![cfg(feature = "sync")]
+
+use rlox_twi::{Interpreter, Lox, Program, Value};
+
+use std::sync::Arc;
+use std::thread;
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn sessions_and_programs_are_send_and_sync() {
+    assert_send_sync::<Lox>();
+    assert_send_sync::<Interpreter>();
+    assert_send_sync::<Program>();
+    assert_send_sync::<Value>();
+}
+
+#[test]
+fn session_moves_between_threads() {
+    let mut lox = Lox::new();
+    lox.set_global("xs", vec![1.0, 2.0]);
+
+    let mut lox = thread::spawn(move || {
+        lox.eval("xs[0] = 10").unwrap();
+        lox
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(lox.get::<Vec<f64>>("xs").unwrap(), vec![10.0, 2.0]);
+    assert_eq!(lox.eval("xs[0] + xs[1]").unwrap().to_string(), "12");
+}
+
+#[test]
+fn program_is_shared_across_threads() {
+    let program = Arc::new(Program::compile("n * n").unwrap());
+
+    let handles: Vec<_> = (0..4)
+        .map(|n| {
+            let program = Arc::clone(&program);
+            thread::spawn(move || {
+                let mut lox = Lox::new();
+                lox.set_global("n", n as f64);
+                lox.run(&program).unwrap().to_string()
+            })
+        })
+        .collect();
+
+    let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    assert_eq!(results, ["0", "1", "4", "9"]);
+}